@@ -1,3 +1,5 @@
+use std::env;
+
 use rustc_version::{version_meta, Channel};
 
 fn main() {
@@ -5,7 +7,20 @@ fn main() {
         println!("cargo:rustc-cfg=nightly");
     }
 
-    if cfg!(target_arch = "x86") || cfg!(target_arch = "x86_64") {
+    // The target is not the host when cross compiling, so `cfg!(target_arch)` can't be used here.
+    let target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    let force_aligned = env::var_os("CARGO_FEATURE_ALIGNED_ACCESS").is_some();
+    let force_unaligned = env::var_os("CARGO_FEATURE_UNALIGNED_ACCESS").is_some();
+
+    let unaligned_access = if force_aligned {
+        false
+    } else if force_unaligned {
+        true
+    } else {
+        matches!(target_arch.as_str(), "x86" | "x86_64" | "aarch64")
+    };
+
+    if unaligned_access {
         println!("cargo:rustc-cfg=unaligned_access");
     }
 }
@@ -0,0 +1,440 @@
+//! A registry of the `t1ha` variants, to record which one produced a persisted hash value.
+//!
+//! Every [`Algorithm`] has a stable numeric [id](Algorithm::id) and [name](Algorithm::name),
+//! which never change between the releases, for example
+//!
+//! ```
+//! use t1ha::{t1ha2_atonce_digest, Algorithm, HashValue};
+//!
+//! let algo: Algorithm = "t1ha2_atonce".parse().unwrap();
+//!
+//! assert_eq!(algo.id(), 6);
+//! assert!(algo.is_portable());
+//! assert_eq!(algo.hash(b"hello world", 0), HashValue::Hash64(t1ha2_atonce_digest(b"hello world", 0)));
+//! ```
+use core::fmt;
+use core::str::FromStr;
+
+use crate::{Hash128, Hash64, T1ha0Impl};
+
+/// A `t1ha` variant.
+///
+/// The discriminants are the stable [ids](Algorithm::id) of the variants.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Algorithm {
+    /// `t1ha0()`, the fastest implementation for the current CPU.
+    ///
+    /// The result depends on the CPU, so it should never be persisted.
+    T1ha0 = 1,
+    /// `t1ha0_32le()`, the little-endian variant for 32-bit CPU.
+    T1ha0_32Le = 2,
+    /// `t1ha0_32be()`, the big-endian variant for 32-bit CPU.
+    T1ha0_32Be = 3,
+    /// `t1ha1_le()`, the little-endian variant for 64-bit CPU.
+    T1ha1Le = 4,
+    /// `t1ha1_be()`, the big-endian variant for 64-bit CPU.
+    T1ha1Be = 5,
+    /// `t1ha2_atonce()`, the at-once variant with 64-bit result.
+    T1ha2Atonce = 6,
+    /// `t1ha2_atonce128()`, the at-once variant with 128-bit result.
+    T1ha2Atonce128 = 7,
+    /// `t1ha0_ia32aes_noavx()` and `t1ha0_ia32aes_avx()`, the AES-NI variant.
+    T1ha0Ia32Aes = 8,
+    /// `t1ha0_ia32aes_avx2()`, the AES-NI variant with AVX2 extensions.
+    T1ha0Ia32AesAvx2 = 9,
+}
+
+/// The byte order in which an [`Algorithm`] reads the data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Endianness {
+    /// Little-endian.
+    Little,
+    /// Big-endian.
+    Big,
+    /// Depends on the implementation chosen for the current CPU.
+    Native,
+}
+
+/// A hash value produced by an [`Algorithm`], of either width.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HashValue {
+    /// A 64-bit hash value.
+    Hash64(Hash64),
+    /// A 128-bit hash value.
+    Hash128(Hash128),
+}
+
+impl HashValue {
+    /// Returns the width of the hash value in bits.
+    pub fn bits(self) -> u32 {
+        match self {
+            HashValue::Hash64(_) => 64,
+            HashValue::Hash128(_) => 128,
+        }
+    }
+
+    /// Returns the hash value widened to 128 bits.
+    pub fn to_u128(self) -> u128 {
+        match self {
+            HashValue::Hash64(h) => u128::from(h.get()),
+            HashValue::Hash128(h) => h.get(),
+        }
+    }
+}
+
+impl From<Hash64> for HashValue {
+    fn from(h: Hash64) -> Self {
+        HashValue::Hash64(h)
+    }
+}
+
+impl From<Hash128> for HashValue {
+    fn from(h: Hash128) -> Self {
+        HashValue::Hash128(h)
+    }
+}
+
+/// Formats the hash value like [`Hash64`] or [`Hash128`].
+impl fmt::Display for HashValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HashValue::Hash64(h) => h.fmt(f),
+            HashValue::Hash128(h) => h.fmt(f),
+        }
+    }
+}
+
+impl Algorithm {
+    /// All the algorithms, ordered by their ids.
+    pub const ALL: [Algorithm; 9] = [
+        Algorithm::T1ha0,
+        Algorithm::T1ha0_32Le,
+        Algorithm::T1ha0_32Be,
+        Algorithm::T1ha1Le,
+        Algorithm::T1ha1Be,
+        Algorithm::T1ha2Atonce,
+        Algorithm::T1ha2Atonce128,
+        Algorithm::T1ha0Ia32Aes,
+        Algorithm::T1ha0Ia32AesAvx2,
+    ];
+
+    /// Returns the stable numeric id of the algorithm.
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns the algorithm with the given id.
+    pub fn from_id(id: u8) -> Option<Algorithm> {
+        Algorithm::ALL.get(usize::from(id).wrapping_sub(1)).copied()
+    }
+
+    /// Returns the stable name of the algorithm, the same as the function that implements it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::T1ha0 => "t1ha0",
+            Algorithm::T1ha0_32Le => "t1ha0_32le",
+            Algorithm::T1ha0_32Be => "t1ha0_32be",
+            Algorithm::T1ha1Le => "t1ha1_le",
+            Algorithm::T1ha1Be => "t1ha1_be",
+            Algorithm::T1ha2Atonce => "t1ha2_atonce",
+            Algorithm::T1ha2Atonce128 => "t1ha2_atonce128",
+            Algorithm::T1ha0Ia32Aes => "t1ha0_ia32aes",
+            Algorithm::T1ha0Ia32AesAvx2 => "t1ha0_ia32aes_avx2",
+        }
+    }
+
+    /// Returns the algorithm with the given name.
+    pub fn from_name(name: &str) -> Option<Algorithm> {
+        Algorithm::ALL
+            .iter()
+            .copied()
+            .find(|algo| algo.name() == name)
+    }
+
+    /// Returns the width of the hash values in bits.
+    pub fn output_bits(self) -> u32 {
+        match self {
+            Algorithm::T1ha2Atonce128 => 128,
+            _ => 64,
+        }
+    }
+
+    /// Returns the byte order in which the data is read.
+    pub fn endianness(self) -> Endianness {
+        match self {
+            Algorithm::T1ha0 => Endianness::Native,
+            Algorithm::T1ha0_32Be | Algorithm::T1ha1Be => Endianness::Big,
+            _ => Endianness::Little,
+        }
+    }
+
+    /// Returns `true` if the algorithm produces the same hash values on every machine,
+    /// so they could be persisted or sent to another machine.
+    ///
+    /// Only `t1ha0()` is not portable, the AES variants fall back to
+    /// [`t1ha0_ia32aes_portable()`](crate::t1ha0_ia32aes_portable) on the CPUs without AES-NI.
+    pub fn is_portable(self) -> bool {
+        self != Algorithm::T1ha0
+    }
+
+    /// Hash the data with the algorithm.
+    pub fn hash(self, data: &[u8], seed: u64) -> HashValue {
+        match self {
+            Algorithm::T1ha0 => crate::t1ha0_digest(data, seed).into(),
+            Algorithm::T1ha0_32Le => crate::t1ha0_32le_digest(data, seed).into(),
+            Algorithm::T1ha0_32Be => crate::t1ha0_32be_digest(data, seed).into(),
+            Algorithm::T1ha1Le => crate::t1ha1_le_digest(data, seed).into(),
+            Algorithm::T1ha1Be => crate::t1ha1_be_digest(data, seed).into(),
+            Algorithm::T1ha2Atonce => crate::t1ha2_atonce_digest(data, seed).into(),
+            Algorithm::T1ha2Atonce128 => crate::t1ha2_atonce128_digest(data, seed).into(),
+            Algorithm::T1ha0Ia32Aes => Hash64(if T1ha0Impl::Ia32Aes.is_available() {
+                unsafe { T1ha0Impl::Ia32Aes.hash_unchecked(data, seed) }
+            } else {
+                crate::t1ha0_ia32aes_portable(data, seed)
+            })
+            .into(),
+            Algorithm::T1ha0Ia32AesAvx2 => Hash64(if T1ha0Impl::Ia32AesAvx2.is_available() {
+                unsafe { T1ha0Impl::Ia32AesAvx2.hash_unchecked(data, seed) }
+            } else {
+                crate::t1ha0_ia32aes_avx2_portable(data, seed)
+            })
+            .into(),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The error returned when parsing an unknown [`Algorithm`] name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseAlgorithmError;
+
+impl fmt::Display for ParseAlgorithmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("unknown t1ha algorithm")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseAlgorithmError {}
+
+impl FromStr for Algorithm {
+    type Err = ParseAlgorithmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Algorithm::from_name(s).ok_or(ParseAlgorithmError)
+    }
+}
+
+/// A hash function selected at runtime, for example from a config file.
+///
+/// The trait is object safe, so the algorithm could be held as a `Box<dyn HashAlgorithm>`.
+pub trait HashAlgorithm {
+    /// Returns the name of the algorithm.
+    fn name(&self) -> &str;
+
+    /// Returns the width of the hash values in bits.
+    fn output_bits(&self) -> u32;
+
+    /// Hash the data with the seed.
+    fn hash(&self, data: &[u8], seed: u64) -> HashValue;
+}
+
+impl HashAlgorithm for Algorithm {
+    fn name(&self) -> &str {
+        Algorithm::name(*self)
+    }
+
+    fn output_bits(&self) -> u32 {
+        Algorithm::output_bits(*self)
+    }
+
+    fn hash(&self, data: &[u8], seed: u64) -> HashValue {
+        Algorithm::hash(*self, data, seed)
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "serde")] {
+        use core::convert::TryFrom;
+
+        use ::serde::{
+            de::{self, Deserialize, Deserializer, Visitor},
+            ser::{Serialize, Serializer},
+        };
+
+        /// Serialize the algorithm as its [name](Algorithm::name).
+        impl Serialize for Algorithm {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                serializer.serialize_str(self.name())
+            }
+        }
+
+        /// Deserialize the algorithm from its [name](Algorithm::name) or [id](Algorithm::id).
+        impl<'de> Deserialize<'de> for Algorithm {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                // Only the self-describing formats could hold either a name or an id.
+                if deserializer.is_human_readable() {
+                    deserializer.deserialize_any(AlgorithmVisitor)
+                } else {
+                    deserializer.deserialize_str(AlgorithmVisitor)
+                }
+            }
+        }
+
+        struct AlgorithmVisitor;
+
+        impl<'de> Visitor<'de> for AlgorithmVisitor {
+            type Value = Algorithm;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("the name or id of a t1ha algorithm")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Algorithm::from_name(v).ok_or_else(|| E::invalid_value(de::Unexpected::Str(v), &self))
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                u8::try_from(v)
+                    .ok()
+                    .and_then(Algorithm::from_id)
+                    .ok_or_else(|| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+            }
+
+            fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                u8::try_from(v)
+                    .ok()
+                    .and_then(Algorithm::from_id)
+                    .ok_or_else(|| E::invalid_value(de::Unexpected::Signed(v), &self))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selfcheck;
+
+    #[test]
+    fn test_ids_and_names() {
+        for (i, &algo) in Algorithm::ALL.iter().enumerate() {
+            assert_eq!(usize::from(algo.id()), i + 1);
+            assert_eq!(Algorithm::from_id(algo.id()), Some(algo));
+            assert_eq!(algo.name().parse(), Ok(algo));
+            assert_eq!(std::format!("{}", algo), algo.name());
+        }
+
+        assert_eq!(Algorithm::from_id(0), None);
+        assert_eq!(Algorithm::from_id(10), None);
+        assert_eq!("t1ha3".parse::<Algorithm>(), Err(ParseAlgorithmError));
+    }
+
+    #[test]
+    fn test_hash() {
+        let _guard = crate::t1ha0::lock_selection();
+        let data = &selfcheck::T1HA_TEST_PATTERN[..];
+
+        for &(algo, reference_values) in &[
+            (Algorithm::T1ha0_32Le, &selfcheck::T1HA_REFVAL_32LE),
+            (Algorithm::T1ha0_32Be, &selfcheck::T1HA_REFVAL_32BE),
+            (Algorithm::T1ha1Le, &selfcheck::T1HA_REFVAL_64LE),
+            (Algorithm::T1ha1Be, &selfcheck::T1HA_REFVAL_64BE),
+            (Algorithm::T1ha2Atonce, &selfcheck::T1HA_REFVAL_2ATONCE),
+            (Algorithm::T1ha0Ia32Aes, &selfcheck::T1HA_REFVAL_IA32AES_A),
+            (
+                Algorithm::T1ha0Ia32AesAvx2,
+                &selfcheck::T1HA_REFVAL_IA32AES_B,
+            ),
+        ] {
+            assert_eq!(
+                algo.hash(data, 0),
+                HashValue::Hash64(Hash64(reference_values[2]))
+            );
+        }
+
+        assert_eq!(
+            Algorithm::T1ha2Atonce128.hash(data, 0),
+            HashValue::Hash128(crate::t1ha2_atonce128_digest(data, 0))
+        );
+        assert_eq!(
+            Algorithm::T1ha0.hash(data, 0),
+            HashValue::Hash64(crate::t1ha0_digest(data, 0))
+        );
+    }
+
+    #[test]
+    fn test_dyn_hash_algorithm() {
+        let algo: std::boxed::Box<dyn HashAlgorithm> =
+            std::boxed::Box::new("t1ha2_atonce128".parse::<Algorithm>().unwrap());
+
+        assert_eq!(algo.name(), "t1ha2_atonce128");
+        assert_eq!(algo.output_bits(), 128);
+        assert_eq!(algo.hash(b"abc", 1).bits(), 128);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        assert_eq!(
+            serde_json::to_string(&Algorithm::T1ha1Le).unwrap(),
+            "\"t1ha1_le\""
+        );
+        assert_eq!(
+            serde_json::from_str::<Algorithm>("\"t1ha2_atonce\"").unwrap(),
+            Algorithm::T1ha2Atonce
+        );
+        assert_eq!(
+            serde_json::from_str::<Algorithm>("7").unwrap(),
+            Algorithm::T1ha2Atonce128
+        );
+        assert!(serde_json::from_str::<Algorithm>("\"t1ha3\"").is_err());
+        assert!(serde_json::from_str::<Algorithm>("0").is_err());
+
+        #[derive(Debug, PartialEq, ::serde::Deserialize)]
+        struct Config {
+            algorithm: Algorithm,
+        }
+
+        assert_eq!(
+            toml::from_str::<Config>("algorithm = 7").unwrap(),
+            Config {
+                algorithm: Algorithm::T1ha2Atonce128
+            }
+        );
+        assert_eq!(
+            toml::from_str::<Config>("algorithm = \"t1ha1_be\"").unwrap(),
+            Config {
+                algorithm: Algorithm::T1ha1Be
+            }
+        );
+        assert!(toml::from_str::<Config>("algorithm = -1").is_err());
+
+        for &algo in &Algorithm::ALL {
+            let bytes = bincode::serialize(&algo).unwrap();
+
+            assert_eq!(bincode::deserialize::<Algorithm>(&bytes).unwrap(), algo);
+        }
+    }
+}
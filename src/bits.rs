@@ -8,8 +8,10 @@ use crate::nightly::likely;
 
 const PAGESIZE: usize = 4096;
 
+// `usize::is_multiple_of` needs Rust 1.87.
+#[allow(unknown_lints, clippy::manual_is_multiple_of)]
 pub fn aligned_to<T, P>(p: *const P) -> bool {
-    (p as usize) % mem::size_of::<T>() == 0
+    (p as usize) % mem::align_of::<T>() == 0
 }
 
 // 'magic' primes
@@ -21,6 +23,42 @@ pub const PRIME_4: u64 = 0x9C06_FAF4_D023_E3AB;
 pub const PRIME_5: u64 = 0xC060_724A_8424_F345;
 pub const PRIME_6: u64 = 0xCB5A_F53A_E3AA_AC31;
 
+/// The tail loads copy only the in-bounds bytes, instead of reading the whole words
+/// before or past the end of the data, which Miri rejects as undefined behaviour.
+pub const SAFE_TAIL: bool = cfg!(any(miri, feature = "safe_tail"));
+
+/// Returns the number of the in-bounds bytes of the tail, from 1 to `size_of::<T>()`.
+#[inline(always)]
+fn tail_len<T>(tail: isize) -> usize {
+    mem::size_of::<T>()
+        - ((mem::size_of::<T>() as isize - tail) & (mem::size_of::<T>() as isize - 1)) as usize
+}
+
+/// Load the in-bounds bytes of the tail as a little-endian word, padded with zeros.
+#[inline(always)]
+unsafe fn safe_tail_le<T: PrimInt + Zero>(p: *const u8, tail: isize) -> T {
+    let mut v = T::zero();
+
+    ptr::copy_nonoverlapping(p, &mut v as *mut T as *mut u8, tail_len::<T>(tail));
+
+    T::from_le(v)
+}
+
+/// Load the in-bounds bytes of the tail as a big-endian word, padded with zeros.
+#[inline(always)]
+unsafe fn safe_tail_be<T: PrimInt + Zero>(p: *const u8, tail: isize) -> T {
+    let mut v = T::zero();
+    let len = tail_len::<T>(tail);
+
+    ptr::copy_nonoverlapping(
+        p,
+        (&mut v as *mut T as *mut u8).add(mem::size_of::<T>() - len),
+        len,
+    );
+
+    T::from_be(v)
+}
+
 #[inline(always)]
 fn can_read_underside<T>(ptr: *const T, size: usize) -> bool {
     ((PAGESIZE - size) & (ptr as usize)) != 0
@@ -55,6 +93,11 @@ where
     #[inline(always)]
     unsafe fn tail<P>(p: *const P, tail: isize) -> Self::Item {
         let p = p as *const u8;
+
+        if SAFE_TAIL {
+            return safe_tail_le(p, tail);
+        }
+
         let shift =
             ((mem::size_of::<T>() as isize - tail) & (mem::size_of::<T>() as isize - 1)) << 3;
 
@@ -78,6 +121,11 @@ where
     #[inline(always)]
     unsafe fn tail<P>(p: *const P, tail: isize) -> Self::Item {
         let p = p as *const u8;
+
+        if SAFE_TAIL {
+            return safe_tail_be(p, tail);
+        }
+
         let shift =
             ((mem::size_of::<T>() as isize - tail) & (mem::size_of::<T>() as isize - 1)) << 3;
 
@@ -99,6 +147,11 @@ where
     #[inline(always)]
     unsafe fn tail<P>(p: *const P, tail: isize) -> Self::Item {
         let p = p as *const u8;
+
+        if SAFE_TAIL {
+            return safe_tail_le(p, tail);
+        }
+
         let offset = (mem::size_of::<T>() as isize - tail) & (mem::size_of::<T>() as isize - 1);
         let shift = offset << 3;
 
@@ -124,6 +177,11 @@ where
     #[inline(always)]
     unsafe fn tail<P>(p: *const P, tail: isize) -> Self::Item {
         let p = p as *const u8;
+
+        if SAFE_TAIL {
+            return safe_tail_be(p, tail);
+        }
+
         let offset = (mem::size_of::<T>() as isize - tail) & (mem::size_of::<T>() as isize - 1);
         let shift = offset << 3;
 
@@ -140,8 +198,9 @@ pub fn rot32(v: u32, n: u32) -> u32 {
     v.rotate_right(n)
 }
 
+/// Rotate the 64-bit value right by `n` bits.
 #[inline(always)]
-pub fn rot64(v: u64, n: u32) -> u64 {
+pub const fn rot64(v: u64, n: u32) -> u64 {
     v.rotate_right(n)
 }
 
@@ -156,26 +215,26 @@ pub fn final32(a: u32, b: u32) -> u64 {
     l
 }
 
+/// The final avalanche of the two 64-bit halves of the `t1ha2` state.
 #[inline(always)]
-pub fn final64(a: u64, b: u64) -> u64 {
+pub const fn final64(a: u64, b: u64) -> u64 {
     let x = a.wrapping_add(rot64(b, 41)).wrapping_mul(PRIME_0);
     let y = rot64(a, 23).wrapping_add(b).wrapping_mul(PRIME_6);
     mux64(x ^ y, PRIME_5)
 }
 
-/// xor-mul-xor mixer
+/// xor-mul-xor mixer, multiply by the prime and xor with the rotated product.
 #[inline(always)]
-pub fn mix64(v: u64, p: u64) -> u64 {
+pub const fn mix64(v: u64, p: u64) -> u64 {
     let v = v.wrapping_mul(p);
     v ^ rot64(v, 41)
 }
 
-/// xor high and low parts of full 128-bit product
+/// xor high and low parts of full 128-bit product of the value and the prime.
 #[inline(always)]
-pub fn mux64(v: u64, prime: u64) -> u64 {
-    let mut h = 0;
-    let l = mul_64x64_128(v, prime, &mut h);
-    l ^ h
+pub const fn mux64(v: u64, prime: u64) -> u64 {
+    let r = (v as u128).wrapping_mul(prime as u128);
+    (r as u64) ^ ((r >> 64) as u64)
 }
 
 #[inline(always)]
@@ -203,3 +262,38 @@ fn mul_64x64_128(a: u64, b: u64, h: &mut u64) -> u64 {
     *h = (r >> 64) as u64;
     r as u64
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_safe_tail() {
+        let data = (1..=32).collect::<std::vec::Vec<u8>>();
+
+        for off in 0..16 {
+            for tail in 1..=16 {
+                let p = data[off..].as_ptr();
+                let len = tail_len::<u64>(tail);
+                let mut le = [0; 8];
+                let mut be = [0; 8];
+
+                le[..len].copy_from_slice(&data[off..off + len]);
+                be[8 - len..].copy_from_slice(&data[off..off + len]);
+
+                unsafe {
+                    assert_eq!(safe_tail_le::<u64>(p, tail), u64::from_le_bytes(le));
+                    assert_eq!(safe_tail_be::<u64>(p, tail), u64::from_be_bytes(be));
+                    assert_eq!(
+                        LittenEndianUnaligned::<u64>::tail(p, tail),
+                        u64::from_le_bytes(le)
+                    );
+                    assert_eq!(
+                        BigEndianUnaligned::<u64>::tail(p, tail),
+                        u64::from_be_bytes(be)
+                    );
+                }
+            }
+        }
+    }
+}
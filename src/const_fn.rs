@@ -0,0 +1,352 @@
+//! The `const fn` variants of the portable hash functions, to hash literals at compile time.
+//!
+//! They read the data byte by byte instead of the `MemoryModel` pointer reads,
+//! so they are slower at runtime, but always return the same values as the runtime functions.
+
+use crate::bits::*;
+
+/// Reads the 64-bit little-endian word at the offset.
+#[inline(always)]
+const fn fetch64(data: &[u8], off: usize) -> u64 {
+    u64::from_le_bytes([
+        data[off],
+        data[off + 1],
+        data[off + 2],
+        data[off + 3],
+        data[off + 4],
+        data[off + 5],
+        data[off + 6],
+        data[off + 7],
+    ])
+}
+
+/// Reads the last 1 to 8 bytes from the offset as a little-endian word, padded with zeros.
+#[inline(always)]
+const fn tail64(data: &[u8], off: usize) -> u64 {
+    let mut v = 0;
+    let mut i = data.len();
+
+    while i > off {
+        i -= 1;
+        v = (v << 8) | data[i] as u64;
+    }
+
+    v
+}
+
+/// The `(a, b)` result of `mixup64(a, b, v, prime)`.
+#[inline(always)]
+const fn mixup64(a: u64, b: u64, v: u64, prime: u64) -> (u64, u64) {
+    let r = (b.wrapping_add(v) as u128).wrapping_mul(prime as u128);
+
+    (a ^ r as u64, b.wrapping_add((r >> 64) as u64))
+}
+
+/// Updates the `t1ha2` state with the 32-byte block at the offset.
+#[inline(always)]
+const fn t1ha2_update(s: [u64; 4], data: &[u8], off: usize) -> [u64; 4] {
+    let [a, b, c, d] = s;
+    let w0 = fetch64(data, off);
+    let w1 = fetch64(data, off + 8);
+    let w2 = fetch64(data, off + 16);
+    let w3 = fetch64(data, off + 24);
+
+    let d02 = w0.wrapping_add(rot64(w2.wrapping_add(d), 56));
+    let c13 = w1.wrapping_add(rot64(w3.wrapping_add(c), 19));
+
+    [
+        a ^ PRIME_5.wrapping_mul(d02.wrapping_add(w3)),
+        b ^ PRIME_6.wrapping_mul(c13.wrapping_add(w2)),
+        c ^ a.wrapping_add(rot64(w0, 57)),
+        d ^ b.wrapping_add(rot64(w1, 38)),
+    ]
+}
+
+/// Returns the state and the offset of the tail after all the 32-byte blocks.
+#[inline(always)]
+const fn t1ha2_loop(mut s: [u64; 4], data: &[u8]) -> ([u64; 4], usize) {
+    let mut off = 0;
+
+    while data.len() - off >= 32 {
+        s = t1ha2_update(s, data, off);
+        off += 32;
+    }
+
+    (s, off)
+}
+
+#[inline(always)]
+const fn t1ha2_init(seed: u64, len: u64) -> [u64; 4] {
+    [
+        seed,
+        len,
+        rot64(len, 23).wrapping_add(!seed),
+        (!len).wrapping_add(rot64(seed, 19)),
+    ]
+}
+
+/// The `const fn` variant of [`t1ha2_atonce()`](crate::t1ha2_atonce).
+///
+/// # Example
+///
+/// ```
+/// use t1ha::{t1ha2_atonce, t1ha2_atonce_const};
+///
+/// const HASH: u64 = t1ha2_atonce_const(b"hello world", 42);
+///
+/// assert_eq!(HASH, t1ha2_atonce(b"hello world", 42));
+/// ```
+pub const fn t1ha2_atonce_const(data: &[u8], seed: u64) -> u64 {
+    let len = data.len();
+    let [mut a, mut b, c, d] = t1ha2_init(seed, len as u64);
+    let mut off = 0;
+
+    if len > 32 {
+        let (s, o) = t1ha2_loop([a, b, c, d], data);
+        off = o;
+
+        // squash
+        a = s[0] ^ PRIME_6.wrapping_mul(s[2].wrapping_add(rot64(s[3], 23)));
+        b = s[1] ^ PRIME_5.wrapping_mul(rot64(s[2], 19).wrapping_add(s[3]));
+    }
+
+    match len - off {
+        25..=32 => {
+            (a, b) = mixup64(a, b, fetch64(data, off), PRIME_4);
+            (b, a) = mixup64(b, a, fetch64(data, off + 8), PRIME_3);
+            (a, b) = mixup64(a, b, fetch64(data, off + 16), PRIME_2);
+            (b, a) = mixup64(b, a, tail64(data, off + 24), PRIME_1);
+        }
+        17..=24 => {
+            (b, a) = mixup64(b, a, fetch64(data, off), PRIME_3);
+            (a, b) = mixup64(a, b, fetch64(data, off + 8), PRIME_2);
+            (b, a) = mixup64(b, a, tail64(data, off + 16), PRIME_1);
+        }
+        9..=16 => {
+            (a, b) = mixup64(a, b, fetch64(data, off), PRIME_2);
+            (b, a) = mixup64(b, a, tail64(data, off + 8), PRIME_1);
+        }
+        1..=8 => {
+            (b, a) = mixup64(b, a, tail64(data, off), PRIME_1);
+        }
+        _ => {}
+    }
+
+    final64(a, b)
+}
+
+/// The `const fn` variant of [`t1ha2_atonce128()`](crate::t1ha2_atonce128).
+///
+/// # Example
+///
+/// ```
+/// use t1ha::{t1ha2_atonce128, t1ha2_atonce128_const};
+///
+/// const HASH: u128 = t1ha2_atonce128_const(b"hello world", 42);
+///
+/// assert_eq!(HASH, t1ha2_atonce128(b"hello world", 42));
+/// ```
+pub const fn t1ha2_atonce128_const(data: &[u8], seed: u64) -> u128 {
+    let len = data.len();
+    let mut s = t1ha2_init(seed, len as u64);
+    let mut off = 0;
+
+    if len > 32 {
+        (s, off) = t1ha2_loop(s, data);
+    }
+
+    let [mut a, mut b, mut c, mut d] = s;
+
+    match len - off {
+        25..=32 => {
+            (a, d) = mixup64(a, d, fetch64(data, off), PRIME_4);
+            (b, a) = mixup64(b, a, fetch64(data, off + 8), PRIME_3);
+            (c, b) = mixup64(c, b, fetch64(data, off + 16), PRIME_2);
+            (d, c) = mixup64(d, c, tail64(data, off + 24), PRIME_1);
+        }
+        17..=24 => {
+            (b, a) = mixup64(b, a, fetch64(data, off), PRIME_3);
+            (c, b) = mixup64(c, b, fetch64(data, off + 8), PRIME_2);
+            (d, c) = mixup64(d, c, tail64(data, off + 16), PRIME_1);
+        }
+        9..=16 => {
+            (c, b) = mixup64(c, b, fetch64(data, off), PRIME_2);
+            (d, c) = mixup64(d, c, tail64(data, off + 8), PRIME_1);
+        }
+        1..=8 => {
+            (d, c) = mixup64(d, c, tail64(data, off), PRIME_1);
+        }
+        _ => {}
+    }
+
+    // final128
+    (a, b) = mixup64(a, b, rot64(c, 41) ^ d, PRIME_0);
+    (b, c) = mixup64(b, c, rot64(d, 23) ^ a, PRIME_6);
+    (c, d) = mixup64(c, d, rot64(a, 19) ^ b, PRIME_5);
+    (d, a) = mixup64(d, a, rot64(b, 31) ^ c, PRIME_4);
+
+    ((c.wrapping_add(d) as u128) << 64) | (a ^ b) as u128
+}
+
+/// The `const fn` variant of [`t1ha1_le()`](crate::t1ha1_le).
+///
+/// # Example
+///
+/// ```
+/// use t1ha::{t1ha1_le, t1ha1_le_const};
+///
+/// const HASH: u64 = t1ha1_le_const(b"hello world", 42);
+///
+/// assert_eq!(HASH, t1ha1_le(b"hello world", 42));
+/// ```
+pub const fn t1ha1_le_const(data: &[u8], seed: u64) -> u64 {
+    let len = data.len();
+    let mut a = seed;
+    let mut b = len as u64;
+    let mut off = 0;
+
+    if len > 32 {
+        let mut c = rot64(len as u64, 17).wrapping_add(seed);
+        let mut d = len as u64 ^ rot64(seed, 17);
+
+        while len - off >= 32 {
+            let w0 = fetch64(data, off);
+            let w1 = fetch64(data, off + 8);
+            let w2 = fetch64(data, off + 16);
+            let w3 = fetch64(data, off + 24);
+            off += 32;
+
+            let d02 = w0 ^ rot64(w2.wrapping_add(d), 17);
+            let c13 = w1 ^ rot64(w3.wrapping_add(c), 17);
+            d = d.wrapping_sub(b ^ rot64(w1, 31));
+            c = c.wrapping_add(a ^ rot64(w0, 41));
+            b ^= PRIME_0.wrapping_mul(c13.wrapping_add(w2));
+            a ^= PRIME_1.wrapping_mul(d02.wrapping_add(w3));
+        }
+
+        a ^= PRIME_6.wrapping_mul(rot64(c, 17).wrapping_add(d));
+        b ^= PRIME_5.wrapping_mul(c.wrapping_add(rot64(d, 17)));
+    }
+
+    match len - off {
+        25..=32 => {
+            b = b.wrapping_add(mux64(fetch64(data, off), PRIME_4));
+            a = a.wrapping_add(mux64(fetch64(data, off + 8), PRIME_3));
+            b = b.wrapping_add(mux64(fetch64(data, off + 16), PRIME_2));
+            a = a.wrapping_add(mux64(tail64(data, off + 24), PRIME_1));
+        }
+        17..=24 => {
+            a = a.wrapping_add(mux64(fetch64(data, off), PRIME_3));
+            b = b.wrapping_add(mux64(fetch64(data, off + 8), PRIME_2));
+            a = a.wrapping_add(mux64(tail64(data, off + 16), PRIME_1));
+        }
+        9..=16 => {
+            b = b.wrapping_add(mux64(fetch64(data, off), PRIME_2));
+            a = a.wrapping_add(mux64(tail64(data, off + 8), PRIME_1));
+        }
+        1..=8 => {
+            a = a.wrapping_add(mux64(tail64(data, off), PRIME_1));
+        }
+        _ => {}
+    }
+
+    // final_weak_avalanche
+    mux64(rot64(a.wrapping_add(b), 17), PRIME_4).wrapping_add(mix64(a ^ b, PRIME_0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{selfcheck, t1ha1_le, t1ha2, t1ha2_atonce, t1ha2_atonce128};
+
+    #[test]
+    fn test_t1ha2_atonce_const() {
+        selfcheck::check(
+            "t1ha2_atonce_const",
+            t1ha2_atonce_const,
+            &selfcheck::T1HA_REFVAL_2ATONCE,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_t1ha2_atonce128_const() {
+        selfcheck::check(
+            "t1ha2_atonce128_const",
+            |data, seed| t1ha2_atonce128_const(data, seed) as u64,
+            &selfcheck::T1HA_REFVAL_2ATONCE128,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_t1ha1_le_const() {
+        selfcheck::check(
+            "t1ha1_le_const",
+            t1ha1_le_const,
+            &selfcheck::T1HA_REFVAL_64LE,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_runtime() {
+        let data = (0..256)
+            .map(|i| (i * 7) as u8)
+            .collect::<std::vec::Vec<_>>();
+
+        for len in 0..data.len() {
+            let data = &data[..len];
+
+            assert_eq!(t1ha2_atonce_const(data, 42), t1ha2_atonce(data, 42));
+            assert_eq!(t1ha2_atonce128_const(data, 42), t1ha2_atonce128(data, 42));
+            assert_eq!(t1ha1_le_const(data, 42), t1ha1_le(data, 42));
+        }
+    }
+
+    #[test]
+    fn test_t1ha2_macro() {
+        const HELLO: u64 = t1ha2!("hello world");
+        const HELLO_SEED: u64 = t1ha2!(b"hello world", 42);
+        const SEED: u64 = 42;
+
+        assert_eq!(HELLO, t1ha2_atonce(b"hello world", 0));
+        assert_eq!(HELLO_SEED, t1ha2_atonce(b"hello world", 42));
+
+        assert_eq!(t1ha2!(""), t1ha2_atonce(b"", 0));
+        assert_eq!(t1ha2!("hello"), t1ha2_atonce(b"hello", 0));
+        assert_eq!(t1ha2!(b"hello world", 42), t1ha2_atonce(b"hello world", 42));
+        assert_eq!(
+            t1ha2!("hello world", 0x2A),
+            t1ha2_atonce(b"hello world", 42)
+        );
+        assert_eq!(
+            t1ha2!("hello world", SEED),
+            t1ha2_atonce(b"hello world", 42)
+        );
+        assert_eq!(
+            t1ha2!("the quick brown fox jumps over the lazy dog"),
+            t1ha2_atonce(b"the quick brown fox jumps over the lazy dog", 0)
+        );
+        assert_eq!(
+            t1ha2!(
+                "a string which is long enough for a few 32-byte blocks and a tail",
+                7
+            ),
+            t1ha2_atonce(
+                b"a string which is long enough for a few 32-byte blocks and a tail",
+                7
+            )
+        );
+
+        let method = |name: &str| match t1ha2_atonce(name.as_bytes(), 0) {
+            t1ha2!("GET") => Some("get"),
+            t1ha2!("POST") => Some("post"),
+            _ => None,
+        };
+
+        assert_eq!(method("GET"), Some("get"));
+        assert_eq!(method("POST"), Some("post"));
+        assert_eq!(method("PUT"), None);
+    }
+}
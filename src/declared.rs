@@ -0,0 +1,167 @@
+//! The streaming hashers of `t1ha1` and `t1ha0_32`, for the data with a length declared in advance.
+//!
+//! Both functions mix the length of the data into their initial state, and hash it in blocks of four words,
+//! so they share the buffering and the length checks, and only differ in their [`BlockState`].
+#![allow(clippy::cast_ptr_alignment)]
+
+use core::fmt;
+use core::mem;
+use core::slice;
+
+use num_traits::{PrimInt, WrappingShr, Zero};
+
+use crate::bits::*;
+
+/// The state of a hash function which hashes the data in blocks of four words.
+pub(crate) trait BlockState: Clone {
+    type Word: PrimInt + Zero + WrappingShr;
+
+    /// Create the initial state for `len` bytes of data.
+    fn new(seed: u64, len: u64) -> Self;
+
+    /// Update the state with a block.
+    unsafe fn update<T: MemoryModel<Item = Self::Word>>(&mut self, v: *const Self::Word);
+
+    /// Update the state with all the blocks of the data, returns the tail.
+    unsafe fn blocks<'a, T: MemoryModel<Item = Self::Word>>(&mut self, data: &'a [u8]) -> &'a [u8];
+
+    /// Mix the state after the blocks, when the data is longer than a block.
+    fn squash(&mut self);
+
+    /// Hash the tail of `len` bytes, at most a block, and returns the hash value.
+    unsafe fn tail<T: MemoryModel<Item = Self::Word>>(
+        &self,
+        v: *const Self::Word,
+        len: usize,
+    ) -> u64;
+}
+
+/// The streaming state machine, which buffers the data up to a block.
+#[derive(Clone, Debug)]
+pub(crate) struct Declared<S: BlockState> {
+    state: S,
+    buffer: [S::Word; 4],
+    partial: usize,
+    len: u64,
+    total: u64,
+    big_endian: bool,
+}
+
+impl<S: BlockState> Declared<S> {
+    /// The size of a block in bytes.
+    const BLOCK_SIZE: usize = mem::size_of::<[S::Word; 4]>();
+
+    pub fn new(seed: u64, len: u64, big_endian: bool) -> Self {
+        Declared {
+            state: S::new(seed, len),
+            buffer: [S::Word::zero(); 4],
+            partial: 0,
+            len,
+            total: 0,
+            big_endian,
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) -> Result<(), LengthMismatch> {
+        self.total = self.total.saturating_add(data.len() as u64);
+
+        if self.total > self.len {
+            return Err(self.mismatch());
+        }
+
+        let block = Self::BLOCK_SIZE;
+
+        while !data.is_empty() {
+            if self.partial == 0 && self.len > block as u64 && data.len() >= block {
+                data = unsafe {
+                    match (
+                        self.big_endian,
+                        !cfg!(unaligned_access) && aligned_to::<S::Word, _>(data.as_ptr()),
+                    ) {
+                        (false, true) => self.state.blocks::<LittenEndianAligned<S::Word>>(data),
+                        (false, false) => self.state.blocks::<LittenEndianUnaligned<S::Word>>(data),
+                        (true, true) => self.state.blocks::<BigEndianAligned<S::Word>>(data),
+                        (true, false) => self.state.blocks::<BigEndianUnaligned<S::Word>>(data),
+                    }
+                };
+                continue;
+            }
+
+            let buf =
+                unsafe { slice::from_raw_parts_mut(self.buffer.as_mut_ptr() as *mut u8, block) };
+            let n = data.len().min(block - self.partial);
+
+            buf[self.partial..self.partial + n].copy_from_slice(&data[..n]);
+            self.partial += n;
+            data = &data[n..];
+
+            if self.partial == block && self.len > block as u64 {
+                unsafe {
+                    if self.big_endian {
+                        self.state
+                            .update::<BigEndianAligned<S::Word>>(self.buffer.as_ptr());
+                    } else {
+                        self.state
+                            .update::<LittenEndianAligned<S::Word>>(self.buffer.as_ptr());
+                    }
+                }
+                self.partial = 0;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(&self) -> Result<u64, LengthMismatch> {
+        if self.total != self.len {
+            return Err(self.mismatch());
+        }
+
+        let mut state = self.state.clone();
+
+        if self.len > Self::BLOCK_SIZE as u64 {
+            state.squash();
+        }
+
+        Ok(unsafe {
+            if self.big_endian {
+                state.tail::<BigEndianAligned<S::Word>>(self.buffer.as_ptr(), self.partial)
+            } else {
+                state.tail::<LittenEndianAligned<S::Word>>(self.buffer.as_ptr(), self.partial)
+            }
+        })
+    }
+
+    fn mismatch(&self) -> LengthMismatch {
+        LengthMismatch {
+            expected: self.len,
+            actual: self.total,
+        }
+    }
+}
+
+/// The error returned when the length of the hashed data differs from the declared length.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LengthMismatch {
+    /// The declared length of the data.
+    pub expected: u64,
+    /// The length of the hashed data.
+    pub actual: u64,
+}
+
+impl fmt::Display for LengthMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "declared {} bytes, but {} bytes were hashed",
+            self.expected, self.actual
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LengthMismatch {}
@@ -0,0 +1,468 @@
+//! Typed hash values, with a canonical byte order and text encoding.
+//!
+//! [`Hash64`] and [`Hash128`] are returned by the `*_digest()` functions.
+//! The canonical bytes are always little-endian, and every encoding of a digest,
+//! the hex text, base64 and the serde bytes, encodes these bytes in order,
+//! so a digest written on one machine is read back the same on any other, for example
+//!
+//! ```
+//! use t1ha::{t1ha2_atonce_digest, Hash64};
+//!
+//! let h = t1ha2_atonce_digest(b"hello world", 0);
+//! let s = h.to_string();
+//!
+//! assert_eq!(s.len(), 16);
+//! assert_eq!(s.parse::<Hash64>().unwrap(), h);
+//! assert_eq!(Hash64::from_bytes(h.to_bytes()), h);
+//!
+//! let hex = h.to_bytes().iter().map(|b| format!("{:02x}", b)).collect::<String>();
+//!
+//! assert_eq!(s, hex);
+//! ```
+//!
+//! Only the `{:x}` and `{:X}` formats print the integer value, most significant digit first.
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::hint::black_box;
+use core::str::FromStr;
+
+use crate::T1ha2Hasher;
+
+/// The error returned when parsing a [`Hash64`] or [`Hash128`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseHashError {
+    /// The text or bytes have a wrong length.
+    InvalidLength(usize),
+    /// The text has a character which is not a hex digit.
+    InvalidDigit,
+    /// The text is not valid base64.
+    InvalidBase64,
+}
+
+impl fmt::Display for ParseHashError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseHashError::InvalidLength(len) => write!(f, "invalid hash length {}", len),
+            ParseHashError::InvalidDigit => f.write_str("invalid hex digit in hash"),
+            ParseHashError::InvalidBase64 => f.write_str("invalid base64 hash"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseHashError {}
+
+macro_rules! hash_value {
+    ($(#[$attr:meta])* $name:ident, $int:ty, $size:expr) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Default)]
+        pub struct $name(pub $int);
+
+        impl $name {
+            /// The size of the hash value in bytes.
+            pub const SIZE: usize = $size;
+
+            /// Returns the hash value as an integer.
+            pub const fn get(self) -> $int {
+                self.0
+            }
+
+            /// Returns the canonical little-endian bytes of the hash value.
+            pub const fn to_bytes(self) -> [u8; $size] {
+                self.0.to_le_bytes()
+            }
+
+            /// Create a hash value from its canonical little-endian bytes.
+            pub const fn from_bytes(bytes: [u8; $size]) -> Self {
+                $name(<$int>::from_le_bytes(bytes))
+            }
+
+            /// Create a hash value from a slice of its canonical little-endian bytes.
+            pub fn from_slice(bytes: &[u8]) -> Result<Self, ParseHashError> {
+                let mut buf = [0; $size];
+
+                if bytes.len() != buf.len() {
+                    return Err(ParseHashError::InvalidLength(bytes.len()));
+                }
+                buf.copy_from_slice(bytes);
+
+                Ok(Self::from_bytes(buf))
+            }
+
+            /// Compare the hash values without an early exit on the first different byte.
+            pub fn ct_eq(&self, other: &Self) -> bool {
+                black_box(self.0 ^ other.0) == 0
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.ct_eq(other)
+            }
+        }
+
+        impl Eq for $name {}
+
+        impl Hash for $name {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0.hash(state)
+            }
+        }
+
+        impl From<$int> for $name {
+            fn from(h: $int) -> Self {
+                $name(h)
+            }
+        }
+
+        impl From<$name> for $int {
+            fn from(h: $name) -> Self {
+                h.0
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}({})", stringify!($name), self)
+            }
+        }
+
+        /// Formats the canonical little-endian bytes as `2 * SIZE` lowercase hex digits.
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                self.to_bytes().iter().try_for_each(|b| write!(f, "{:02x}", b))
+            }
+        }
+
+        /// Formats the integer value, unlike `Display`.
+        impl fmt::LowerHex for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::LowerHex::fmt(&self.0, f)
+            }
+        }
+
+        /// Formats the integer value, unlike `Display`.
+        impl fmt::UpperHex for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::UpperHex::fmt(&self.0, f)
+            }
+        }
+
+        /// Parses the canonical little-endian bytes from exactly `2 * SIZE` hex digits, in either case.
+        impl FromStr for $name {
+            type Err = ParseHashError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                if s.len() != 2 * $size {
+                    return Err(ParseHashError::InvalidLength(s.len()));
+                }
+
+                let mut bytes = [0; $size];
+
+                for (b, digits) in bytes.iter_mut().zip(s.as_bytes().chunks(2)) {
+                    let hi = (digits[0] as char).to_digit(16).ok_or(ParseHashError::InvalidDigit)?;
+                    let lo = (digits[1] as char).to_digit(16).ok_or(ParseHashError::InvalidDigit)?;
+
+                    *b = (hi << 4 | lo) as u8;
+                }
+
+                Ok(Self::from_bytes(bytes))
+            }
+        }
+
+        #[cfg(feature = "base64")]
+        impl $name {
+            /// Returns the canonical bytes encoded as URL-safe base64 without padding.
+            pub fn to_base64(&self) -> std::string::String {
+                URL_SAFE_NO_PAD.encode(self.to_bytes())
+            }
+
+            /// Decode the hash value from the canonical bytes encoded as URL-safe base64 without padding.
+            pub fn from_base64(s: &str) -> Result<Self, ParseHashError> {
+                let mut buf = [0; $size + 3];
+                let len = URL_SAFE_NO_PAD
+                    .decode_slice(s, &mut buf)
+                    .map_err(|_| ParseHashError::InvalidBase64)?;
+
+                Self::from_slice(&buf[..len])
+            }
+        }
+
+        /// Serialize the hash value as the hex string in the human-readable formats,
+        /// and as the canonical bytes otherwise.
+        #[cfg(feature = "serde")]
+        impl ::serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: ::serde::Serializer,
+            {
+                if serializer.is_human_readable() {
+                    serializer.collect_str(self)
+                } else {
+                    serializer.serialize_bytes(&self.to_bytes())
+                }
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> ::serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: ::serde::Deserializer<'de>,
+            {
+                let visitor = HashVisitor(core::marker::PhantomData::<$name>);
+
+                if deserializer.is_human_readable() {
+                    deserializer.deserialize_str(visitor)
+                } else {
+                    deserializer.deserialize_bytes(visitor)
+                }
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl HashEncoding for $name {
+            const SIZE: usize = $size;
+
+            fn parse(s: &str) -> Result<Self, ParseHashError> {
+                s.parse()
+            }
+
+            fn from_slice(bytes: &[u8]) -> Result<Self, ParseHashError> {
+                $name::from_slice(bytes)
+            }
+        }
+    };
+}
+
+hash_value!(
+    /// A 64-bit hash value.
+    ///
+    /// It is formatted and parsed as the hex of its little-endian bytes,
+    /// so `Hash64(0x0102_0304_0506_0708)` is displayed as `0807060504030201`.
+    Hash64,
+    u64,
+    8
+);
+
+hash_value!(
+    /// A 128-bit hash value.
+    ///
+    /// It is formatted and parsed as the hex of its little-endian bytes, like [`Hash64`].
+    Hash128,
+    u128,
+    16
+);
+
+#[cfg(feature = "base64")]
+use ::base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+cfg_if! {
+    if #[cfg(feature = "serde")] {
+        use core::marker::PhantomData;
+
+        use ::serde::de::{self, SeqAccess, Visitor};
+
+        trait HashEncoding: Sized {
+            const SIZE: usize;
+
+            fn parse(s: &str) -> Result<Self, ParseHashError>;
+
+            fn from_slice(bytes: &[u8]) -> Result<Self, ParseHashError>;
+        }
+
+        struct HashVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: HashEncoding> Visitor<'de> for HashVisitor<T> {
+            type Value = T;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{} hex digits or {} bytes", 2 * T::SIZE, T::SIZE)
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                T::parse(v).map_err(E::custom)
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                T::from_slice(v).map_err(E::custom)
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut buf = [0; 16];
+                let mut len = 0;
+
+                while let Some(b) = seq.next_element()? {
+                    if len == T::SIZE {
+                        return Err(de::Error::invalid_length(len + 1, &self));
+                    }
+
+                    buf[len] = b;
+                    len += 1;
+                }
+
+                self.visit_bytes(&buf[..len])
+            }
+        }
+    }
+}
+
+/// `t1ha0()` returning a typed hash value.
+pub fn t1ha0_digest(data: &[u8], seed: u64) -> Hash64 {
+    Hash64(crate::t1ha0(data, seed))
+}
+
+/// `t1ha0_32le()` returning a typed hash value.
+pub fn t1ha0_32le_digest(data: &[u8], seed: u64) -> Hash64 {
+    Hash64(crate::t1ha0_32le(data, seed))
+}
+
+/// `t1ha0_32be()` returning a typed hash value.
+pub fn t1ha0_32be_digest(data: &[u8], seed: u64) -> Hash64 {
+    Hash64(crate::t1ha0_32be(data, seed))
+}
+
+/// `t1ha1_le()` returning a typed hash value.
+pub fn t1ha1_le_digest(data: &[u8], seed: u64) -> Hash64 {
+    Hash64(crate::t1ha1_le(data, seed))
+}
+
+/// `t1ha1_be()` returning a typed hash value.
+pub fn t1ha1_be_digest(data: &[u8], seed: u64) -> Hash64 {
+    Hash64(crate::t1ha1_be(data, seed))
+}
+
+/// `t1ha2_atonce()` returning a typed hash value.
+pub fn t1ha2_atonce_digest(data: &[u8], seed: u64) -> Hash64 {
+    Hash64(crate::t1ha2_atonce(data, seed))
+}
+
+/// `t1ha2_atonce128()` returning a typed hash value.
+pub fn t1ha2_atonce128_digest(data: &[u8], seed: u64) -> Hash128 {
+    Hash128(crate::t1ha2_atonce128(data, seed))
+}
+
+impl T1ha2Hasher {
+    /// Returns the 64-bit hash value for the values written so far, as a typed hash value.
+    pub fn finish_digest(&self) -> Hash64 {
+        Hash64(self.finish())
+    }
+
+    /// Returns the 128-bit hash value for the values written so far, as a typed hash value.
+    pub fn finish128_digest(&self) -> Hash128 {
+        Hash128(self.finish128())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn test_bytes() {
+        let h = Hash64(0x0102_0304_0506_0708);
+
+        assert_eq!(h.to_bytes(), [8, 7, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(Hash64::from_bytes(h.to_bytes()), h);
+        assert_eq!(Hash64::from_slice(&h.to_bytes()), Ok(h));
+        assert_eq!(
+            Hash64::from_slice(&[1, 2, 3]),
+            Err(ParseHashError::InvalidLength(3))
+        );
+
+        let h = Hash128(0x0102_0304_0506_0708_090A_0B0C_0D0E_0F10);
+
+        assert_eq!(h.to_bytes()[0], 0x10);
+        assert_eq!(Hash128::from_bytes(h.to_bytes()), h);
+    }
+
+    #[test]
+    fn test_hex() {
+        let h = Hash64(0xAB);
+
+        assert_eq!(h.to_string(), "ab00000000000000");
+        assert_eq!(std::format!("{:?}", h), "Hash64(ab00000000000000)");
+        assert_eq!(std::format!("{:016x}", h), "00000000000000ab");
+        assert_eq!("ab00000000000000".parse(), Ok(h));
+        assert_eq!("AB00000000000000".parse(), Ok(h));
+        assert_eq!(
+            "ab".parse::<Hash64>(),
+            Err(ParseHashError::InvalidLength(2))
+        );
+        assert_eq!(
+            "+0000000000000ab".parse::<Hash64>(),
+            Err(ParseHashError::InvalidDigit)
+        );
+        assert_eq!(
+            "00000000000000é".parse::<Hash64>(),
+            Err(ParseHashError::InvalidDigit)
+        );
+
+        let h = t1ha2_atonce128_digest(b"hello world", 42);
+
+        assert_eq!(h.to_string().len(), 32);
+        assert_eq!(h.to_string().parse(), Ok(h));
+
+        let hex = h
+            .to_bytes()
+            .iter()
+            .map(|b| std::format!("{:02x}", b))
+            .collect::<std::string::String>();
+
+        assert_eq!(h.to_string(), hex);
+    }
+
+    #[test]
+    fn test_digest() {
+        let data = b"hello world";
+
+        assert_eq!(
+            t1ha2_atonce_digest(data, 1).get(),
+            crate::t1ha2_atonce(data, 1)
+        );
+        assert_eq!(t1ha1_le_digest(data, 1).get(), crate::t1ha1_le(data, 1));
+
+        let mut h = T1ha2Hasher::with_seeds(1, 1);
+        h.update(data);
+
+        assert_eq!(h.finish_digest().get(), h.finish());
+        assert_eq!(h.finish128_digest().get(), h.finish128());
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64() {
+        let h = Hash128(0x0102_0304_0506_0708_090A_0B0C_0D0E_0F10);
+        let s = h.to_base64();
+
+        assert_eq!(s.len(), 22);
+        assert_eq!(Hash128::from_base64(&s), Ok(h));
+        assert_eq!(Hash64::from_base64(&s), Err(ParseHashError::InvalidBase64));
+        assert_eq!(
+            Hash64::from_base64("AQID"),
+            Err(ParseHashError::InvalidLength(3))
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let h = Hash64(0x0102_0304_0506_0708);
+        let json = serde_json::to_string(&h).unwrap();
+
+        assert_eq!(json, "\"0807060504030201\"");
+        assert_eq!(serde_json::from_str::<Hash64>(&json).unwrap(), h);
+        assert!(serde_json::from_str::<Hash64>("\"0102\"").is_err());
+        assert!(serde_json::from_str::<Hash128>(&json).is_err());
+    }
+}
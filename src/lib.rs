@@ -20,18 +20,52 @@
 extern crate std;
 #[macro_use]
 extern crate cfg_if;
+// The procedural macros refer to `::t1ha`, which is this crate in the tests.
+#[cfg(test)]
+extern crate self as t1ha;
 
 use core::hash::{BuildHasherDefault, Hasher};
 
+pub mod algorithm;
 mod bits;
+mod const_fn;
+mod declared;
+mod digest_value;
+pub mod mix;
 mod nightly;
-mod t1ha0;
+pub mod selfcheck;
+#[cfg(all(feature = "serde", feature = "std"))]
+pub mod serde;
+mod stable;
+mod state;
+pub mod t1ha0;
+mod t1ha0_soft_aes;
 mod t1ha1;
-mod t1ha2;
+pub mod t1ha2;
+
+pub use algorithm::{Algorithm, HashAlgorithm, HashValue};
+pub use const_fn::{t1ha1_le_const, t1ha2_atonce128_const, t1ha2_atonce_const};
+pub use declared::LengthMismatch;
+pub use digest_value::{
+    t1ha0_32be_digest, t1ha0_32le_digest, t1ha0_digest, t1ha1_be_digest, t1ha1_le_digest,
+    t1ha2_atonce128_digest, t1ha2_atonce_digest, Hash128, Hash64, ParseHashError,
+};
+pub use stable::{stable_hash128, StableHash, StableHasher};
+pub use state::T1haSeededState;
+pub use t1ha0::{t1ha0_32be, t1ha0_32le, T1ha0Impl, T1ha0_32Hasher};
+pub use t1ha0_soft_aes::{t1ha0_ia32aes_avx2_portable, t1ha0_ia32aes_portable};
+pub use t1ha1::{t1ha1_be, t1ha1_le, t1ha1_u64, T1ha1Hasher};
+pub use t1ha2::{
+    t1ha2_atonce, t1ha2_atonce128, t1ha2_u128, t1ha2_u64, StateError, T1ha2Hasher, T1ha2Key,
+};
+pub use t1ha_derive::t1ha2;
+#[cfg(feature = "derive")]
+pub use t1ha_derive::StableHash;
 
-pub use t1ha0::{t1ha0_32be, t1ha0_32le};
-pub use t1ha1::{t1ha1_be, t1ha1_le};
-pub use t1ha2::{t1ha2_atonce, t1ha2_atonce128, T1ha2Hasher};
+#[cfg(feature = "digest_atonce")]
+pub use t1ha2::{T1ha2Atonce128, T1ha2Atonce64};
+#[cfg(feature = "digest")]
+pub use t1ha2::{T1ha2Stream128, T1ha2Stream64};
 
 cfg_if! {
     if #[cfg(target_endian = "little")] {
@@ -45,19 +79,16 @@ cfg_if! {
 
 cfg_if! {
     if #[cfg(any(target_arch = "x86", target_arch = "x86_64"))] {
-        cfg_if! {
-            if #[cfg(any(target_feature = "aes", target_feature = "avx", target_feature = "avx2"))] {
-                mod t1ha0_aes;
-            }
-        }
+        mod t1ha0_aes;
+
         cfg_if! {
             if #[cfg(target_feature = "aes")] {
-                pub use t1ha0_aes::t1ha0_ia32aes as t1ha0_ia32aes_noavx;
+                pub use t1ha0_aes::t1ha0_ia32aes_noavx;
             }
         }
         cfg_if! {
-            if #[cfg(target_feature = "avx")] {
-                pub use t1ha0_aes::t1ha0_ia32aes as t1ha0_ia32aes_avx;
+            if #[cfg(any(target_feature = "aes", target_feature = "avx"))] {
+                pub use t1ha0_aes::t1ha0_ia32aes_avx;
             }
         }
         cfg_if! {
@@ -68,39 +99,129 @@ cfg_if! {
     }
 }
 
+/// The size of the buffer which holds the small writes of `T1haHasher`.
+const T1HA_HASHER_BUFFER_SIZE: usize = 64;
+
 /// An implementation of the `t1ha` hash function.
 ///
+/// The small writes, like the fields of a composite key, are buffered,
+/// and hashed with `t1ha0()` at once when the hasher is finished.
+///
 /// See the [crate documentation](index.html) for more details.
-#[derive(Clone, Copy, Debug, Default)]
-pub struct T1haHasher(u64);
+#[derive(Clone, Copy, Debug)]
+pub struct T1haHasher {
+    hash: u64,
+    buffer: [u8; T1HA_HASHER_BUFFER_SIZE],
+    len: usize,
+}
+
+impl Default for T1haHasher {
+    #[inline]
+    fn default() -> Self {
+        Self::with_seed(0)
+    }
+}
 
 impl T1haHasher {
     /// Create a `t1ha` hasher starting with a state corresponding to the hash `key`.
     #[inline]
     pub fn with_seed(seed: u64) -> Self {
-        Self(seed)
+        T1haHasher {
+            hash: seed,
+            buffer: [0; T1HA_HASHER_BUFFER_SIZE],
+            len: 0,
+        }
+    }
+
+    /// Hash the buffered bytes, the result is used as the seed of the following bytes.
+    #[inline]
+    fn flush(&mut self) {
+        if self.len > 0 {
+            self.hash = t1ha0(&self.buffer[..self.len], self.hash);
+            self.len = 0;
+        }
     }
 }
 
+macro_rules! write_int {
+    ($($name:ident: $ty:ty),*) => {
+        $(
+            #[inline]
+            fn $name(&mut self, i: $ty) {
+                self.write(&i.to_le_bytes())
+            }
+        )*
+    };
+}
+
 impl Hasher for T1haHasher {
     #[inline]
     fn finish(&self) -> u64 {
-        self.0
+        // the seed is always mixed, even if nothing was written, so a random seed doesn't leak.
+        match self.len {
+            // a single `u64` key, hashed with the straight-line function equal to the selected implementation.
+            8 => {
+                let mut x = [0; 8];
+                x.copy_from_slice(&self.buffer[..8]);
+
+                match t1ha0::selected() {
+                    // `t1ha1()` of the native-endian bytes equals `t1ha1_u64()` of the value.
+                    T1ha0Impl::Portable64 => t1ha1_u64(u64::from_ne_bytes(x), self.hash),
+                    // the AES variants hash up to 32 bytes like `t1ha2_atonce()`.
+                    T1ha0Impl::Ia32Aes | T1ha0Impl::Ia32AesAvx | T1ha0Impl::Ia32AesAvx2 => {
+                        t1ha2_u64(u64::from_le_bytes(x), self.hash)
+                    }
+                    _ => t1ha0(&x, self.hash),
+                }
+            }
+            _ => t1ha0(&self.buffer[..self.len], self.hash),
+        }
     }
 
     #[inline]
     fn write(&mut self, bytes: &[u8]) {
-        *self = T1haHasher(t1ha0(bytes, self.0));
+        if self.len + bytes.len() > T1HA_HASHER_BUFFER_SIZE {
+            self.flush();
+
+            if bytes.len() > T1HA_HASHER_BUFFER_SIZE {
+                self.hash = t1ha0(bytes, self.hash);
+                return;
+            }
+        }
+
+        self.buffer[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    write_int! {
+        write_u8: u8,
+        write_u16: u16,
+        write_u32: u32,
+        write_u64: u64,
+        write_u128: u128,
+        write_usize: usize,
+        write_i8: i8,
+        write_i16: i16,
+        write_i32: i32,
+        write_i64: i64,
+        write_i128: i128,
+        write_isize: isize
     }
 }
 
 /// A builder for default `t1ha` hashers.
 pub type T1haBuildHasher = BuildHasherDefault<T1haHasher>;
 
+/// A builder for default `t1ha2` hashers.
+pub type T1ha2BuildHasher = BuildHasherDefault<T1ha2Hasher>;
+
 cfg_if! {
     if #[cfg(feature = "std")] {
         use std::collections::{HashMap, HashSet};
 
+        pub use state::{T1ha2RandomState, T1haRandomState};
+        pub use t1ha2::{HashingReader, HashingWriter};
+
         /// A `HashMap` using a default `t1ha` hasher.
         ///
         /// # Example
@@ -134,6 +255,96 @@ cfg_if! {
         /// set.insert(2);
         /// ```
         pub type T1haHashSet<T> = HashSet<T, T1haBuildHasher>;
+
+        /// A `HashMap` using a default `t1ha2` hasher.
+        ///
+        /// Unlike `T1haHashMap`, the hash values do not depend on the CPU features.
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// use t1ha::T1ha2HashMap;
+        ///
+        /// let mut map = T1ha2HashMap::default();
+        /// map.insert(1, "one");
+        /// map.insert(2, "two");
+        ///
+        /// map = T1ha2HashMap::with_capacity_and_hasher(10, Default::default());
+        /// map.insert(1, "one");
+        /// map.insert(2, "two");
+        /// ```
+        pub type T1ha2HashMap<K, V> = HashMap<K, V, T1ha2BuildHasher>;
+
+        /// A `HashSet` using a default `t1ha2` hasher.
+        ///
+        /// Unlike `T1haHashSet`, the hash values do not depend on the CPU features.
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// use t1ha::T1ha2HashSet;
+        ///
+        /// let mut set = T1ha2HashSet::default();
+        /// set.insert(1);
+        /// set.insert(2);
+        ///
+        /// set = T1ha2HashSet::with_capacity_and_hasher(10, Default::default());
+        /// set.insert(1);
+        /// set.insert(2);
+        /// ```
+        pub type T1ha2HashSet<T> = HashSet<T, T1ha2BuildHasher>;
+
+        /// A `HashMap` using a randomly seeded `t1ha` hasher.
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// use t1ha::T1haRandomHashMap;
+        ///
+        /// let mut map = T1haRandomHashMap::default();
+        /// map.insert(1, "one");
+        /// map.insert(2, "two");
+        /// ```
+        pub type T1haRandomHashMap<K, V> = HashMap<K, V, T1haRandomState>;
+
+        /// A `HashSet` using a randomly seeded `t1ha` hasher.
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// use t1ha::T1haRandomHashSet;
+        ///
+        /// let mut set = T1haRandomHashSet::default();
+        /// set.insert(1);
+        /// set.insert(2);
+        /// ```
+        pub type T1haRandomHashSet<T> = HashSet<T, T1haRandomState>;
+
+        /// A `HashMap` using a randomly seeded `t1ha2` hasher.
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// use t1ha::T1ha2RandomHashMap;
+        ///
+        /// let mut map = T1ha2RandomHashMap::default();
+        /// map.insert(1, "one");
+        /// map.insert(2, "two");
+        /// ```
+        pub type T1ha2RandomHashMap<K, V> = HashMap<K, V, T1ha2RandomState>;
+
+        /// A `HashSet` using a randomly seeded `t1ha2` hasher.
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// use t1ha::T1ha2RandomHashSet;
+        ///
+        /// let mut set = T1ha2RandomHashSet::default();
+        /// set.insert(1);
+        /// set.insert(2);
+        /// ```
+        pub type T1ha2RandomHashSet<T> = HashSet<T, T1ha2RandomState>;
     }
 }
 
@@ -142,56 +353,110 @@ cfg_if! {
 /// But actual CPU's features/capabilities and may be significantly different,
 /// especially on x86 platform. Therefore, internally, t1ha0() may require
 /// dynamic dispatching for choice best implementation.
+///
+/// See the [`t1ha0`](t1ha0/index.html) module to inspect or pin the chosen implementation.
+#[inline]
 pub fn t1ha0(data: &[u8], seed: u64) -> u64 {
-    prefer::HASH(data, seed)
+    unsafe { t1ha0::selected().hash_unchecked(data, seed) }
 }
 
-mod prefer {
-    cfg_if! {
-        if #[cfg(target_pointer_width = "64")] {
-            pub use super::t1ha1 as t1ha0_fallback;
-        } else {
-            pub use super::t1ha0_32 as t1ha0_fallback;
-        }
-    }
+#[cfg(test)]
+mod tests {
+    use core::hash::{BuildHasher, Hash};
+
+    use super::*;
 
-    cfg_if! {
-        if #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), feature = "runtime_select", feature = "std"))] {
-            use std::is_x86_feature_detected;
+    #[test]
+    fn test_t1ha_hasher() {
+        let _lock = t1ha0::lock_selection();
+        let data = (0..256).map(|i| i as u8).collect::<std::vec::Vec<_>>();
 
-            use lazy_static::lazy_static;
+        assert_eq!(T1haHasher::with_seed(42).finish(), t1ha0(&[], 42));
 
-            use super::*;
+        for len in 0..data.len() {
+            let mut h = T1haHasher::with_seed(42);
+            h.write(&data[..len]);
 
-            lazy_static! {
-                pub static ref HASH: fn(data: &[u8], seed: u64) -> u64 = t1ha0_resolve();
+            if len > T1HA_HASHER_BUFFER_SIZE {
+                assert_eq!(h.finish(), t1ha0(&[], t1ha0(&data[..len], 42)));
+            } else {
+                assert_eq!(h.finish(), t1ha0(&data[..len], 42));
             }
+        }
+    }
 
-            #[cfg(not(target_feature = "aes"))]
-            pub use self::t1ha0_fallback as t1ha0_ia32aes_noavx;
+    #[test]
+    fn test_t1ha_hasher_small_writes() {
+        let _lock = t1ha0::lock_selection();
+        let mut h = T1haHasher::with_seed(42);
+        h.write_u8(1);
+        h.write_u16(2);
+        h.write_u32(3);
+        h.write_u64(4);
+        h.write_u128(5);
+        h.write_usize(6);
 
-            #[cfg(not(target_feature = "avx"))]
-            pub use self::t1ha0_fallback as t1ha0_ia32aes_avx;
+        let mut data = std::vec::Vec::new();
+        data.extend_from_slice(&1u8.to_le_bytes());
+        data.extend_from_slice(&2u16.to_le_bytes());
+        data.extend_from_slice(&3u32.to_le_bytes());
+        data.extend_from_slice(&4u64.to_le_bytes());
+        data.extend_from_slice(&5u128.to_le_bytes());
+        data.extend_from_slice(&6usize.to_le_bytes());
 
-            #[cfg(not(target_feature = "avx2"))]
-            pub use self::t1ha0_fallback as t1ha0_ia32aes_avx2;
+        assert_eq!(h.finish(), t1ha0(&data, 42));
+    }
 
-            fn t1ha0_resolve() -> fn(&[u8], u64) -> u64 {
-                if is_x86_feature_detected!("avx2") {
-                    t1ha0_ia32aes_avx2
-                } else if is_x86_feature_detected!("avx") {
-                    t1ha0_ia32aes_avx
-                } else if is_x86_feature_detected!("aes") {
-                    t1ha0_ia32aes_noavx
-                } else {
-                    t1ha0_fallback
-                }
+    #[test]
+    fn test_t1ha_hasher_u64() {
+        let _lock = t1ha0::lock_selection();
+
+        for imp in t1ha0::available() {
+            t1ha0::force(imp).unwrap();
+
+            for &x in &[0, 1, 42, 0x0123_4567_89AB_CDEF, u64::MAX] {
+                let mut h = T1haHasher::with_seed(42);
+                h.write_u64(x);
+                assert_eq!(h.finish(), t1ha0(&x.to_le_bytes(), 42), "{}", imp);
+
+                // the slow path of the same key, written in two parts.
+                let mut h = T1haHasher::with_seed(42);
+                h.write(&x.to_le_bytes()[..3]);
+                h.write(&x.to_le_bytes()[3..]);
+                assert_eq!(h.finish(), t1ha0(&x.to_le_bytes(), 42), "{}", imp);
             }
-        } else {
-            pub use self::t1ha0_fallback as HASH;
         }
+
+        t1ha0::reset();
     }
-}
 
-#[cfg(test)]
-mod selfcheck;
+    #[test]
+    fn test_t1ha_hasher_composite_key() {
+        let _lock = t1ha0::lock_selection();
+        let state = T1haBuildHasher::default();
+        let key = (1u32, 2u64, "three");
+
+        // `Hash` of the tuple writes the fields in order, and `str` ends with a `0xff` byte.
+        let mut key_bytes = std::vec::Vec::new();
+        key_bytes.extend_from_slice(&1u32.to_le_bytes());
+        key_bytes.extend_from_slice(&2u64.to_le_bytes());
+        key_bytes.extend_from_slice(b"three\xff");
+
+        assert_eq!(state.hash_one(key), t1ha0(&key_bytes, 0));
+        assert_ne!(state.hash_one(key), state.hash_one((1u32, 2u64, "thre")));
+        assert_ne!(state.hash_one(key), state.hash_one((2u32, 1u64, "three")));
+
+        let long = [7u8; 200];
+        let mut h = T1haHasher::default();
+        key.hash(&mut h);
+        long[..].hash(&mut h);
+        key.hash(&mut h);
+
+        // The buffered key and the length of `long` are flushed before `long` is hashed at once.
+        let mut prefix = key_bytes.clone();
+        prefix.extend_from_slice(&long.len().to_le_bytes());
+        let seed = t1ha0(&long, t1ha0(&prefix, 0));
+
+        assert_eq!(h.finish(), t1ha0(&key_bytes, seed));
+    }
+}
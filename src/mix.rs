@@ -0,0 +1,20 @@
+//! The mixing primitives of `t1ha`, to build custom hashes of fixed-size values.
+//!
+//! `PRIME_0` to `PRIME_6` are the 'magic' 64-bit primes of the reference implementation,
+//! which the hash functions pass as the multipliers of `mix64()` and `mux64()`.
+//!
+//! # Example
+//!
+//! ```
+//! use t1ha::mix::{mix64, mux64, PRIME_0, PRIME_1};
+//!
+//! fn hash_pair(x: u64, y: u64) -> u64 {
+//!     mux64(mix64(x, PRIME_0) ^ y, PRIME_1)
+//! }
+//!
+//! assert_ne!(hash_pair(1, 2), hash_pair(2, 1));
+//! ```
+
+pub use crate::bits::{
+    final64, mix64, mux64, rot64, PRIME_0, PRIME_1, PRIME_2, PRIME_3, PRIME_4, PRIME_5, PRIME_6,
+};
@@ -0,0 +1,767 @@
+//! Hash any `serde::Serialize` value, without an intermediate buffer.
+//!
+//! [`Serializer`] streams a self-describing encoding of the value into a [`StableHasher`],
+//! so the fingerprints are the same on every platform, and could be persisted, for example
+//!
+//! ```
+//! use std::collections::BTreeMap;
+//!
+//! let mut config = BTreeMap::new();
+//! config.insert("jobs", 8u32);
+//!
+//! let fingerprint = t1ha::serde::hash128(&config).unwrap();
+//! # let _ = fingerprint;
+//! ```
+//!
+//! # Stability rules
+//!
+//! The encoding never changes between the releases:
+//!
+//! - Every value starts with a tag byte of its type in the serde data model.
+//! - Integers are written as little-endian with their own width, `f32` and `f64` with a canonical NaN and `-0.0` as `0.0`,
+//!   see [`StableHash`].
+//! - Strings and byte arrays are prefixed with their length.
+//! - The names of the structs, tuple structs and unit structs are **not** included, so renaming a type keeps its fingerprints.
+//! - Newtype structs are transparent, they hash the same as the wrapped value.
+//! - The names of the struct fields **are** included, in the order of serialization,
+//!   so renaming or reordering the fields changes the fingerprints.
+//! - Enum variants are written by their (serialized) name, not their index,
+//!   so reordering the variants keeps the fingerprints, while renaming them doesn't.
+//! - Tuples, tuple structs and structs are prefixed with the number of their fields.
+//! - Sequences are followed by an end marker and the number of their elements,
+//!   so the encoding doesn't depend on whether the length is known up front.
+//! - Every map entry is hashed with a new hasher, and the map is written as the number of the entries
+//!   and the sum of their hash values, so a `HashMap` hashes the same regardless of its iteration order.
+//!   **A `HashSet` is serialized as a sequence, so it does depend on the iteration order**,
+//!   serialize it with [`serialize_unordered`] instead.
+//! - The serializer is not human-readable, so the types like [`Hash64`](crate::Hash64) are serialized as bytes.
+use core::fmt;
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+use ::serde::ser::{self, Serialize};
+
+use crate::{StableHash, StableHasher};
+
+mod tag {
+    pub const END: u8 = 0;
+    pub const BOOL: u8 = 1;
+    pub const I8: u8 = 2;
+    pub const I16: u8 = 3;
+    pub const I32: u8 = 4;
+    pub const I64: u8 = 5;
+    pub const I128: u8 = 6;
+    pub const U8: u8 = 7;
+    pub const U16: u8 = 8;
+    pub const U32: u8 = 9;
+    pub const U64: u8 = 10;
+    pub const U128: u8 = 11;
+    pub const F32: u8 = 12;
+    pub const F64: u8 = 13;
+    pub const CHAR: u8 = 14;
+    pub const STR: u8 = 15;
+    pub const BYTES: u8 = 16;
+    pub const NONE: u8 = 17;
+    pub const SOME: u8 = 18;
+    pub const UNIT: u8 = 19;
+    pub const UNIT_STRUCT: u8 = 20;
+    pub const UNIT_VARIANT: u8 = 21;
+    pub const NEWTYPE_VARIANT: u8 = 22;
+    pub const SEQ: u8 = 23;
+    pub const TUPLE: u8 = 24;
+    pub const TUPLE_STRUCT: u8 = 25;
+    pub const TUPLE_VARIANT: u8 = 26;
+    pub const MAP: u8 = 27;
+    pub const STRUCT: u8 = 28;
+    pub const STRUCT_VARIANT: u8 = 29;
+}
+
+/// Returns the 64-bit fingerprint of the value with the zero seed.
+///
+/// **A `HashSet` in the value makes the fingerprint irreproducible**, because it is serialized
+/// in its iteration order, which differs between the instances. See [`serialize_unordered`].
+pub fn hash64<T: Serialize + ?Sized>(value: &T) -> Result<u64, Error> {
+    let mut hasher = StableHasher::new();
+    value.serialize(Serializer::new(&mut hasher))?;
+    Ok(hasher.finish())
+}
+
+/// Returns the 128-bit fingerprint of the value with the zero seed.
+///
+/// **A `HashSet` in the value makes the fingerprint irreproducible**, because it is serialized
+/// in its iteration order, which differs between the instances. See [`serialize_unordered`].
+pub fn hash128<T: Serialize + ?Sized>(value: &T) -> Result<u128, Error> {
+    let mut hasher = StableHasher::new();
+    value.serialize(Serializer::new(&mut hasher))?;
+    Ok(hasher.finish128())
+}
+
+/// Serialize the elements of a collection, like a `HashSet`, as a sequence in the order of their fingerprints,
+/// so the fingerprint of the collection doesn't depend on its iteration order.
+///
+/// It is meant for `#[serde(serialize_with = "t1ha::serde::serialize_unordered")]`,
+/// and applies to every serializer, which all see the elements in the same order.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+///
+/// #[derive(serde::Serialize)]
+/// struct Build {
+///     #[serde(serialize_with = "t1ha::serde::serialize_unordered")]
+///     features: HashSet<&'static str>,
+/// }
+///
+/// let features = ["std", "serde", "digest"];
+/// let forward = Build { features: features.iter().copied().collect() };
+/// let backward = Build { features: features.iter().rev().copied().collect() };
+///
+/// assert_eq!(t1ha::serde::hash128(&forward), t1ha::serde::hash128(&backward));
+/// ```
+pub fn serialize_unordered<'a, C, T, S>(collection: &'a C, serializer: S) -> Result<S::Ok, S::Error>
+where
+    C: ?Sized,
+    &'a C: IntoIterator<Item = &'a T>,
+    T: Serialize + ?Sized + 'a,
+    S: ser::Serializer,
+{
+    let mut elements = collection
+        .into_iter()
+        .map(|element| Ok((hash128(&element)?, element)))
+        .collect::<Result<Vec<_>, Error>>()
+        .map_err(ser::Error::custom)?;
+
+    elements.sort_by_key(|&(fingerprint, _)| fingerprint);
+
+    serializer.collect_seq(elements.into_iter().map(|(_, element)| element))
+}
+
+/// The error returned by the `Serialize` implementation of the value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+/// A `serde::Serializer` which feeds the value into a [`StableHasher`].
+pub struct Serializer<'a> {
+    hasher: &'a mut StableHasher,
+}
+
+impl<'a> Serializer<'a> {
+    /// Create a serializer which feeds the values into the hasher.
+    pub fn new(hasher: &'a mut StableHasher) -> Self {
+        Serializer { hasher }
+    }
+
+    fn write<T: StableHash + ?Sized>(self, tag: u8, v: &T) -> Result<(), Error> {
+        self.hasher.write_u8(tag);
+        v.stable_hash(self.hasher);
+        Ok(())
+    }
+}
+
+/// The state of a compound value, which counts its elements.
+pub struct Compound<'a> {
+    hasher: &'a mut StableHasher,
+    len: usize,
+}
+
+impl<'a> Compound<'a> {
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.len += 1;
+        value.serialize(Serializer::new(self.hasher))
+    }
+
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), Error> {
+        self.hasher.write_str(key);
+        self.element(value)
+    }
+
+    fn end_seq(self) -> Result<(), Error> {
+        self.hasher.write_u8(tag::END);
+        self.hasher.write_len(self.len);
+        Ok(())
+    }
+}
+
+/// The state of a map, which sums the hash values of its entries.
+pub struct MapCompound<'a> {
+    hasher: &'a mut StableHasher,
+    len: usize,
+    sum: u128,
+    entry: Option<StableHasher>,
+}
+
+impl<'a> ser::Serializer for Serializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = MapCompound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.write(tag::BOOL, &v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.write(tag::I8, &v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.write(tag::I16, &v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.write(tag::I32, &v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.write(tag::I64, &v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), Error> {
+        self.write(tag::I128, &v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.write(tag::U8, &v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.write(tag::U16, &v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.write(tag::U32, &v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.write(tag::U64, &v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), Error> {
+        self.write(tag::U128, &v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.write(tag::F32, &v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.write(tag::F64, &v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.write(tag::CHAR, &v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.write(tag::STR, v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        self.hasher.write_u8(tag::BYTES);
+        self.hasher.write_len(v.len());
+        self.hasher.write(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.hasher.write_u8(tag::NONE);
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        self.hasher.write_u8(tag::SOME);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.hasher.write_u8(tag::UNIT);
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.hasher.write_u8(tag::UNIT_STRUCT);
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.write(tag::UNIT_VARIANT, variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.hasher.write_u8(tag::NEWTYPE_VARIANT);
+        self.hasher.write_str(variant);
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>, Error> {
+        self.hasher.write_u8(tag::SEQ);
+        Ok(Compound {
+            hasher: self.hasher,
+            len: 0,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a>, Error> {
+        self.hasher.write_u8(tag::TUPLE);
+        self.hasher.write_len(len);
+        Ok(Compound {
+            hasher: self.hasher,
+            len: 0,
+        })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        self.hasher.write_u8(tag::TUPLE_STRUCT);
+        self.hasher.write_len(len);
+        Ok(Compound {
+            hasher: self.hasher,
+            len: 0,
+        })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        self.hasher.write_u8(tag::TUPLE_VARIANT);
+        self.hasher.write_str(variant);
+        self.hasher.write_len(len);
+        Ok(Compound {
+            hasher: self.hasher,
+            len: 0,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapCompound<'a>, Error> {
+        self.hasher.write_u8(tag::MAP);
+        Ok(MapCompound {
+            hasher: self.hasher,
+            len: 0,
+            sum: 0,
+            entry: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Compound<'a>, Error> {
+        self.hasher.write_u8(tag::STRUCT);
+        self.hasher.write_len(len);
+        Ok(Compound {
+            hasher: self.hasher,
+            len: 0,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        self.hasher.write_u8(tag::STRUCT_VARIANT);
+        self.hasher.write_str(variant);
+        self.hasher.write_len(len);
+        Ok(Compound {
+            hasher: self.hasher,
+            len: 0,
+        })
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.end_seq()
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for MapCompound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        if self.entry.is_some() {
+            return Err(ser::Error::custom("map key without a value"));
+        }
+
+        let entry = self.entry.insert(self.hasher.fork());
+
+        key.serialize(Serializer::new(entry))
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let mut entry = self
+            .entry
+            .take()
+            .ok_or_else(|| <Error as ser::Error>::custom("map value without a key"))?;
+
+        value.serialize(Serializer::new(&mut entry))?;
+
+        self.len += 1;
+        self.sum = self.sum.wrapping_add(entry.finish128());
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), Error> {
+        if self.entry.is_some() {
+            return Err(ser::Error::custom("map key without a value"));
+        }
+
+        self.hasher.write_len(self.len);
+        self.hasher.write_u128(self.sum);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+    use std::vec;
+
+    use ::serde::Serialize;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Config {
+        name: &'static str,
+        jobs: usize,
+        features: vec::Vec<&'static str>,
+        mode: Mode,
+    }
+
+    #[derive(Serialize)]
+    enum Mode {
+        Debug,
+        Release { lto: bool },
+    }
+
+    #[derive(Serialize)]
+    enum Reordered {
+        Release { lto: bool },
+        Debug,
+    }
+
+    #[derive(Serialize)]
+    struct Renamed {
+        title: &'static str,
+    }
+
+    #[derive(Serialize)]
+    struct Named {
+        name: &'static str,
+    }
+
+    #[derive(Serialize)]
+    struct Meters(u64);
+
+    #[test]
+    fn test_pinned() {
+        let config = Config {
+            name: "release",
+            jobs: 8,
+            features: vec!["std", "serde"],
+            mode: Mode::Release { lto: true },
+        };
+
+        // The encoding must never change, this value is the same on every platform.
+        assert_eq!(
+            hash128(&config).unwrap(),
+            0x23A9_41F5_2C76_E72F_7B38_A0F3_BF27_3C2A
+        );
+        assert_eq!(hash64(&config).unwrap(), {
+            let mut hasher = StableHasher::new();
+            config.serialize(Serializer::new(&mut hasher)).unwrap();
+            hasher.finish()
+        });
+    }
+
+    #[test]
+    fn test_names() {
+        assert_ne!(
+            hash128(&Named { name: "x" }),
+            hash128(&Renamed { title: "x" })
+        );
+        assert_eq!(hash128(&Mode::Debug), hash128(&Reordered::Debug));
+        assert_eq!(
+            hash128(&Mode::Release { lto: false }),
+            hash128(&Reordered::Release { lto: false })
+        );
+        assert_eq!(hash128(&Meters(5)), hash128(&5u64));
+    }
+
+    #[test]
+    fn test_framing() {
+        assert_ne!(hash128(&("ab", "c")), hash128(&("a", "bc")));
+        assert_ne!(
+            hash128(&vec![vec![1u8], vec![2, 3]]),
+            hash128(&vec![vec![1u8, 2], vec![3]])
+        );
+        assert_ne!(hash128(&Some(())), hash128(&None::<()>));
+        assert_ne!(hash128(&1u32), hash128(&1u64));
+        assert_eq!(hash128(&-0.0f64), hash128(&0.0f64));
+    }
+
+    #[test]
+    fn test_maps() {
+        let entries = (0..100u32).map(|i| (i, i * i)).collect::<vec::Vec<_>>();
+
+        let forward = entries.iter().copied().collect::<HashMap<_, _>>();
+        let backward = entries.iter().rev().copied().collect::<HashMap<_, _>>();
+        let ordered = entries.iter().copied().collect::<BTreeMap<_, _>>();
+
+        assert_eq!(hash128(&forward), hash128(&backward));
+        assert_eq!(hash128(&forward), hash128(&ordered));
+
+        let mut changed = ordered.clone();
+        changed.insert(0, 1);
+
+        assert_ne!(hash128(&ordered), hash128(&changed));
+    }
+
+    #[derive(Serialize)]
+    struct Features {
+        #[serde(serialize_with = "serialize_unordered")]
+        set: HashSet<u32>,
+    }
+
+    #[test]
+    fn test_sets() {
+        let elements = (0..100u32).collect::<vec::Vec<_>>();
+
+        // every `HashSet` has its own random state, so the iteration orders differ.
+        let forward = elements.iter().copied().collect::<HashSet<_>>();
+        let backward = elements.iter().rev().copied().collect::<HashSet<_>>();
+
+        if !forward.iter().eq(backward.iter()) {
+            assert_ne!(hash128(&forward), hash128(&backward));
+        }
+
+        let forward = Features { set: forward };
+        let backward = Features { set: backward };
+
+        assert_eq!(hash128(&forward), hash128(&backward));
+
+        // the other serializers see the same order of the elements.
+        let json = serde_json::to_string(&forward).unwrap();
+        assert_eq!(json, serde_json::to_string(&backward).unwrap());
+
+        let ordered = elements.iter().copied().collect::<BTreeSet<_>>();
+        let mut decoded = serde_json::from_str::<BTreeMap<&str, vec::Vec<u32>>>(&json).unwrap();
+        assert_eq!(
+            decoded
+                .remove("set")
+                .unwrap()
+                .into_iter()
+                .collect::<BTreeSet<_>>(),
+            ordered
+        );
+
+        let mut changed = forward.set.clone();
+        changed.remove(&0);
+        changed.insert(100);
+        assert_ne!(hash128(&forward), hash128(&Features { set: changed }));
+    }
+
+    #[test]
+    fn test_unknown_length() {
+        struct Iter;
+
+        impl Serialize for Iter {
+            fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_seq((0..10u8).filter(|i| i % 2 == 0))
+            }
+        }
+
+        assert_eq!(hash128(&Iter), hash128(&[0u8, 2, 4, 6, 8][..]));
+    }
+
+    #[test]
+    fn test_error() {
+        struct Broken;
+
+        impl Serialize for Broken {
+            fn serialize<S: ser::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+                Err(ser::Error::custom("broken"))
+            }
+        }
+
+        assert_eq!(hash64(&vec![Broken]).unwrap_err().to_string(), "broken");
+    }
+
+    #[test]
+    fn test_malformed_map() {
+        use ::serde::ser::SerializeMap;
+
+        /// Serializes a map with the entries, `None` for a missing key or value.
+        struct Malformed(&'static [(Option<u8>, Option<u8>)]);
+
+        impl Serialize for Malformed {
+            fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut map = serializer.serialize_map(None)?;
+
+                for (key, value) in self.0 {
+                    if let Some(key) = key {
+                        map.serialize_key(key)?;
+                    }
+                    if let Some(value) = value {
+                        map.serialize_value(value)?;
+                    }
+                }
+
+                map.end()
+            }
+        }
+
+        assert!(hash64(&Malformed(&[(Some(1), Some(2))])).is_ok());
+        assert_eq!(
+            hash64(&Malformed(&[(Some(1), None), (Some(2), Some(3))]))
+                .unwrap_err()
+                .to_string(),
+            "map key without a value"
+        );
+        assert_eq!(
+            hash64(&Malformed(&[(Some(1), Some(2)), (Some(3), None)]))
+                .unwrap_err()
+                .to_string(),
+            "map key without a value"
+        );
+        assert_eq!(
+            hash64(&Malformed(&[(None, Some(2))]))
+                .unwrap_err()
+                .to_string(),
+            "map value without a key"
+        );
+    }
+}
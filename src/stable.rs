@@ -0,0 +1,595 @@
+//! Structural hashing of Rust values, with the same result on every platform.
+//!
+//! The `Hash` implementations of the standard library write `usize` and the native-endian integers,
+//! so the same value hashes differently on the 32/64-bit or little/big-endian hosts.
+//! [`StableHasher`] writes every integer as little-endian with a fixed width, `usize` and `isize` as 64 bits,
+//! and hashes the data with [`T1ha2Hasher`], so the 128-bit result is identical everywhere, for example
+//!
+//! ```
+//! use t1ha::{stable_hash128, StableHash, StableHasher};
+//!
+//! struct Config {
+//!     name: String,
+//!     threads: usize,
+//! }
+//!
+//! impl StableHash for Config {
+//!     fn stable_hash(&self, hasher: &mut StableHasher) {
+//!         self.name.stable_hash(hasher);
+//!         self.threads.stable_hash(hasher);
+//!     }
+//! }
+//!
+//! let config = Config { name: "release".to_owned(), threads: 8 };
+//!
+//! assert_eq!(stable_hash128(&config), stable_hash128(&("release", 8u64)));
+//! ```
+//!
+//! With the `derive` feature, the trait could be derived with `#[derive(StableHash)]`.
+use core::marker::PhantomData;
+use core::time::Duration;
+
+use crate::T1ha2Hasher;
+
+/// A hasher which writes the values in a platform-independent encoding.
+///
+/// The encoding of the [`StableHash`] implementations never changes between the releases.
+#[derive(Clone, Debug)]
+pub struct StableHasher {
+    seed: u64,
+    hasher: T1ha2Hasher,
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+macro_rules! write_int {
+    ($($(#[$attr:meta])* $name:ident($ty:ty)),* $(,)*) => {
+        $(
+            $(#[$attr])*
+            #[inline]
+            pub fn $name(&mut self, i: $ty) {
+                self.write(&i.to_le_bytes())
+            }
+        )*
+    };
+}
+
+impl StableHasher {
+    /// Create a hasher with the zero seed.
+    pub fn new() -> Self {
+        Self::with_seed(0)
+    }
+
+    /// Create a hasher with the seed.
+    pub fn with_seed(seed: u64) -> Self {
+        StableHasher {
+            seed,
+            hasher: T1ha2Hasher::with_seeds(seed, seed),
+        }
+    }
+
+    /// Write the bytes as is, without the length.
+    #[inline]
+    pub fn write(&mut self, bytes: &[u8]) {
+        self.hasher.update(bytes)
+    }
+
+    write_int! {
+        /// Write a `u8`.
+        write_u8(u8),
+        /// Write a `u16` as little-endian.
+        write_u16(u16),
+        /// Write a `u32` as little-endian.
+        write_u32(u32),
+        /// Write a `u64` as little-endian.
+        write_u64(u64),
+        /// Write a `u128` as little-endian.
+        write_u128(u128),
+        /// Write an `i8`.
+        write_i8(i8),
+        /// Write an `i16` as little-endian.
+        write_i16(i16),
+        /// Write an `i32` as little-endian.
+        write_i32(i32),
+        /// Write an `i64` as little-endian.
+        write_i64(i64),
+        /// Write an `i128` as little-endian.
+        write_i128(i128),
+    }
+
+    /// Write a `usize` as a little-endian `u64`.
+    #[inline]
+    pub fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64)
+    }
+
+    /// Write an `isize` as a little-endian `i64`.
+    #[inline]
+    pub fn write_isize(&mut self, i: isize) {
+        self.write_i64(i as i64)
+    }
+
+    /// Write the length of a sequence, which frames its elements.
+    #[inline]
+    pub fn write_len(&mut self, len: usize) {
+        self.write_usize(len)
+    }
+
+    /// Write the length and the UTF-8 bytes of the string.
+    #[inline]
+    pub fn write_str(&mut self, s: &str) {
+        self.write_len(s.len());
+        self.write(s.as_bytes())
+    }
+
+    /// Write the number of the items and the sum of their hash values,
+    /// which doesn't depend on the order of the items.
+    ///
+    /// Every item is hashed with a new hasher with the same seed.
+    pub fn write_unordered<I>(&mut self, items: I)
+    where
+        I: IntoIterator,
+        I::Item: StableHash,
+    {
+        let (len, sum) = items.into_iter().fold((0, 0u128), |(len, sum), item| {
+            let mut hasher = self.fork();
+            item.stable_hash(&mut hasher);
+
+            (len + 1, sum.wrapping_add(hasher.finish128()))
+        });
+
+        self.write_len(len);
+        self.write_u128(sum)
+    }
+
+    /// Returns a new hasher with the same seed.
+    pub(crate) fn fork(&self) -> StableHasher {
+        StableHasher::with_seed(self.seed)
+    }
+
+    /// Returns the 64-bit hash value for the values written so far.
+    pub fn finish(&self) -> u64 {
+        self.hasher.finish()
+    }
+
+    /// Returns the 128-bit hash value for the values written so far.
+    pub fn finish128(&self) -> u128 {
+        self.hasher.finish128()
+    }
+}
+
+/// A value which could be hashed with [`StableHasher`].
+///
+/// Unlike `Hash`, the encoding is the same on every platform.
+pub trait StableHash {
+    /// Feed the value into the hasher.
+    fn stable_hash(&self, hasher: &mut StableHasher);
+}
+
+/// Returns the 128-bit stable hash value of the value with the zero seed.
+pub fn stable_hash128<T: StableHash + ?Sized>(value: &T) -> u128 {
+    let mut hasher = StableHasher::new();
+    value.stable_hash(&mut hasher);
+    hasher.finish128()
+}
+
+macro_rules! impl_int {
+    ($($ty:ty => $write:ident),*) => {
+        $(
+            impl StableHash for $ty {
+                #[inline]
+                fn stable_hash(&self, hasher: &mut StableHasher) {
+                    hasher.$write(*self)
+                }
+            }
+        )*
+    };
+}
+
+impl_int! {
+    u8 => write_u8, u16 => write_u16, u32 => write_u32, u64 => write_u64, u128 => write_u128,
+    usize => write_usize,
+    i8 => write_i8, i16 => write_i16, i32 => write_i32, i64 => write_i64, i128 => write_i128,
+    isize => write_isize
+}
+
+impl StableHash for bool {
+    #[inline]
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write_u8(*self as u8)
+    }
+}
+
+impl StableHash for char {
+    #[inline]
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write_u32(*self as u32)
+    }
+}
+
+/// All the NaNs hash as the canonical quiet NaN, and `-0.0` hashes as `0.0`.
+impl StableHash for f32 {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write_u32(if self.is_nan() {
+            0x7FC0_0000
+        } else if *self == 0.0 {
+            0
+        } else {
+            self.to_bits()
+        })
+    }
+}
+
+/// All the NaNs hash as the canonical quiet NaN, and `-0.0` hashes as `0.0`.
+impl StableHash for f64 {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write_u64(if self.is_nan() {
+            0x7FF8_0000_0000_0000
+        } else if *self == 0.0 {
+            0
+        } else {
+            self.to_bits()
+        })
+    }
+}
+
+impl StableHash for str {
+    #[inline]
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write_str(self)
+    }
+}
+
+impl StableHash for () {
+    #[inline]
+    fn stable_hash(&self, _hasher: &mut StableHasher) {}
+}
+
+impl<T: ?Sized> StableHash for PhantomData<T> {
+    #[inline]
+    fn stable_hash(&self, _hasher: &mut StableHasher) {}
+}
+
+impl StableHash for Duration {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write_u64(self.as_secs());
+        hasher.write_u32(self.subsec_nanos());
+    }
+}
+
+impl<T: StableHash + ?Sized> StableHash for &T {
+    #[inline]
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        (**self).stable_hash(hasher)
+    }
+}
+
+impl<T: StableHash + ?Sized> StableHash for &mut T {
+    #[inline]
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        (**self).stable_hash(hasher)
+    }
+}
+
+/// The length is written before the elements.
+impl<T: StableHash> StableHash for [T] {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write_len(self.len());
+
+        for item in self {
+            item.stable_hash(hasher);
+        }
+    }
+}
+
+/// Hashes the same as a slice.
+impl<T: StableHash, const N: usize> StableHash for [T; N] {
+    #[inline]
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self[..].stable_hash(hasher)
+    }
+}
+
+impl<T: StableHash> StableHash for Option<T> {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        match self {
+            None => hasher.write_u8(0),
+            Some(v) => {
+                hasher.write_u8(1);
+                v.stable_hash(hasher)
+            }
+        }
+    }
+}
+
+impl<T: StableHash, E: StableHash> StableHash for Result<T, E> {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        match self {
+            Ok(v) => {
+                hasher.write_u8(0);
+                v.stable_hash(hasher)
+            }
+            Err(e) => {
+                hasher.write_u8(1);
+                e.stable_hash(hasher)
+            }
+        }
+    }
+}
+
+macro_rules! impl_tuple {
+    ($(($($name:ident)+))+) => {
+        $(
+            impl<$($name: StableHash),+> StableHash for ($($name,)+) {
+                #[allow(non_snake_case)]
+                fn stable_hash(&self, hasher: &mut StableHasher) {
+                    let ($(ref $name,)+) = *self;
+                    $($name.stable_hash(hasher);)+
+                }
+            }
+        )+
+    };
+}
+
+impl_tuple! {
+    (A)
+    (A B)
+    (A B C)
+    (A B C D)
+    (A B C D E)
+    (A B C D E F)
+    (A B C D E F G)
+    (A B C D E F G H)
+    (A B C D E F G H I)
+    (A B C D E F G H I J)
+    (A B C D E F G H I J K)
+    (A B C D E F G H I J K L)
+}
+
+cfg_if! {
+    if #[cfg(feature = "std")] {
+        use std::borrow::{Cow, ToOwned};
+        use std::boxed::Box;
+        use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+        use std::rc::Rc;
+        use std::string::String;
+        use std::sync::Arc;
+        use std::vec::Vec;
+
+        macro_rules! impl_deref {
+            ($($ty:ident),*) => {
+                $(
+                    impl<T: StableHash + ?Sized> StableHash for $ty<T> {
+                        #[inline]
+                        fn stable_hash(&self, hasher: &mut StableHasher) {
+                            (**self).stable_hash(hasher)
+                        }
+                    }
+                )*
+            };
+        }
+
+        impl_deref!(Box, Rc, Arc);
+
+        impl<T: StableHash + ToOwned + ?Sized> StableHash for Cow<'_, T> {
+            #[inline]
+            fn stable_hash(&self, hasher: &mut StableHasher) {
+                (**self).stable_hash(hasher)
+            }
+        }
+
+        impl StableHash for String {
+            #[inline]
+            fn stable_hash(&self, hasher: &mut StableHasher) {
+                hasher.write_str(self)
+            }
+        }
+
+        /// Hashes the same as a slice.
+        impl<T: StableHash> StableHash for Vec<T> {
+            #[inline]
+            fn stable_hash(&self, hasher: &mut StableHasher) {
+                self[..].stable_hash(hasher)
+            }
+        }
+
+        /// Hashes the same as a slice.
+        impl<T: StableHash> StableHash for VecDeque<T> {
+            fn stable_hash(&self, hasher: &mut StableHasher) {
+                hasher.write_len(self.len());
+
+                for item in self {
+                    item.stable_hash(hasher);
+                }
+            }
+        }
+
+        /// The entries are hashed in the order of the keys.
+        impl<K: StableHash, V: StableHash> StableHash for BTreeMap<K, V> {
+            fn stable_hash(&self, hasher: &mut StableHasher) {
+                hasher.write_len(self.len());
+
+                for entry in self {
+                    entry.stable_hash(hasher);
+                }
+            }
+        }
+
+        /// The items are hashed in their order.
+        impl<T: StableHash> StableHash for BTreeSet<T> {
+            fn stable_hash(&self, hasher: &mut StableHasher) {
+                hasher.write_len(self.len());
+
+                for item in self {
+                    item.stable_hash(hasher);
+                }
+            }
+        }
+
+        /// The hash value doesn't depend on the iteration order, see [`StableHasher::write_unordered()`].
+        impl<K: StableHash, V: StableHash, S> StableHash for HashMap<K, V, S> {
+            #[inline]
+            fn stable_hash(&self, hasher: &mut StableHasher) {
+                hasher.write_unordered(self)
+            }
+        }
+
+        /// The hash value doesn't depend on the iteration order, see [`StableHasher::write_unordered()`].
+        impl<T: StableHash, S> StableHash for HashSet<T, S> {
+            #[inline]
+            fn stable_hash(&self, hasher: &mut StableHasher) {
+                hasher.write_unordered(self)
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeMap, HashMap, HashSet};
+    use std::string::{String, ToString};
+    use std::vec;
+
+    #[test]
+    fn test_pinned() {
+        let value = (
+            42u8,
+            -1i32,
+            1_000_000usize,
+            1.5f64,
+            "hello",
+            vec![Some('x'), None],
+            [0u16; 3],
+        );
+
+        // The encoding must never change, these values are the same on every platform.
+        assert_eq!(
+            stable_hash128(&()),
+            0xA86C_EEAE_ABBF_CDE1_CD28_01D3_B922_37D6
+        );
+        assert_eq!(
+            stable_hash128(&value),
+            0x20D6_7D81_4269_5171_4C71_3538_C4E6_4E67
+        );
+    }
+
+    #[test]
+    fn test_integer_width() {
+        assert_eq!(stable_hash128(&7usize), stable_hash128(&7u64));
+        assert_eq!(stable_hash128(&-7isize), stable_hash128(&-7i64));
+        assert_ne!(stable_hash128(&7u32), stable_hash128(&7u64));
+    }
+
+    #[test]
+    fn test_floats() {
+        assert_eq!(stable_hash128(&-0.0f64), stable_hash128(&0.0f64));
+        assert_eq!(stable_hash128(&-0.0f32), stable_hash128(&0.0f32));
+        assert_eq!(
+            stable_hash128(&f64::NAN),
+            stable_hash128(&f64::from_bits(0xFFF8_0000_0000_0001))
+        );
+        assert_eq!(stable_hash128(&f32::NAN), stable_hash128(&-f32::NAN));
+        assert_ne!(stable_hash128(&1.0f64), stable_hash128(&-1.0f64));
+    }
+
+    #[test]
+    fn test_framing() {
+        assert_ne!(stable_hash128(&("ab", "c")), stable_hash128(&("a", "bc")));
+        assert_ne!(
+            stable_hash128(&(vec![1u8], vec![2u8, 3])),
+            stable_hash128(&(vec![1u8, 2], vec![3u8]))
+        );
+        assert_eq!(stable_hash128("abc"), stable_hash128(&"abc".to_string()));
+        assert_eq!(stable_hash128(&[1u32, 2]), stable_hash128(&vec![1u32, 2]));
+    }
+
+    #[test]
+    fn test_unordered() {
+        let items = (0..100u32)
+            .map(|i| (i, i.to_string()))
+            .collect::<vec::Vec<_>>();
+
+        let forward = items.iter().cloned().collect::<HashMap<_, _>>();
+        let backward = items.iter().rev().cloned().collect::<HashMap<_, _>>();
+        let ordered = items.iter().cloned().collect::<BTreeMap<_, _>>();
+
+        assert_eq!(stable_hash128(&forward), stable_hash128(&backward));
+        assert_ne!(stable_hash128(&forward), stable_hash128(&ordered));
+
+        let mut changed = forward.clone();
+        changed.insert(0, String::from("changed"));
+
+        assert_ne!(stable_hash128(&forward), stable_hash128(&changed));
+
+        let a = [1u8, 2, 3].iter().copied().collect::<HashSet<_>>();
+        let b = [3u8, 1, 2].iter().copied().collect::<HashSet<_>>();
+
+        assert_eq!(stable_hash128(&a), stable_hash128(&b));
+    }
+
+    #[test]
+    fn test_seed() {
+        let mut a = StableHasher::with_seed(1);
+        let mut b = StableHasher::with_seed(2);
+        "abc".stable_hash(&mut a);
+        "abc".stable_hash(&mut b);
+
+        assert_ne!(a.finish128(), b.finish128());
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive() {
+        use crate::StableHash;
+
+        #[derive(StableHash)]
+        struct Config<'a, T> {
+            name: &'a str,
+            values: vec::Vec<T>,
+            #[stable_hash(skip)]
+            _cache: Option<u64>,
+        }
+
+        #[derive(StableHash)]
+        struct Unit;
+
+        #[derive(StableHash)]
+        struct Tuple(u8, i64);
+
+        #[derive(StableHash)]
+        enum Mode {
+            Fast,
+            Level(u8),
+            Custom { name: String },
+        }
+
+        let config = Config {
+            name: "release",
+            values: vec![1u32, 2],
+            _cache: Some(1),
+        };
+
+        assert_eq!(
+            stable_hash128(&config),
+            stable_hash128(&("release", vec![1u32, 2]))
+        );
+        assert_eq!(stable_hash128(&Unit), stable_hash128(&()));
+        assert_eq!(stable_hash128(&Tuple(1, 2)), stable_hash128(&(1u8, 2i64)));
+        assert_eq!(stable_hash128(&Mode::Fast), stable_hash128(&0u32));
+        assert_eq!(
+            stable_hash128(&Mode::Level(3)),
+            stable_hash128(&(1u32, 3u8))
+        );
+        assert_eq!(
+            stable_hash128(&Mode::Custom {
+                name: "x".to_string()
+            }),
+            stable_hash128(&(2u32, "x"))
+        );
+    }
+}
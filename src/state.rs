@@ -0,0 +1,247 @@
+//! Builders of hashers with random or explicit seeds.
+use core::hash::BuildHasher;
+
+use crate::{T1ha2Hasher, T1ha2Key, T1haHasher};
+
+/// A builder of `t1ha` hashers which start from the given seed.
+///
+/// The hash values are deterministic, use [`T1haRandomState`] if the keys may be chosen by an attacker.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// use t1ha::T1haSeededState;
+///
+/// let mut map = HashMap::with_hasher(T1haSeededState::new(42));
+/// map.insert(1, "one");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct T1haSeededState {
+    seed: u64,
+}
+
+impl T1haSeededState {
+    /// Create a builder of `t1ha` hashers starting from the seed.
+    #[inline]
+    pub fn new(seed: u64) -> Self {
+        T1haSeededState { seed }
+    }
+}
+
+impl BuildHasher for T1haSeededState {
+    type Hasher = T1haHasher;
+
+    #[inline]
+    fn build_hasher(&self) -> T1haHasher {
+        T1haHasher::with_seed(self.seed)
+    }
+}
+
+impl BuildHasher for T1ha2Key {
+    type Hasher = T1ha2Hasher;
+
+    #[inline]
+    fn build_hasher(&self) -> T1ha2Hasher {
+        self.hasher()
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "std")] {
+        use core::fmt;
+        use std::cell::Cell;
+        use std::collections::hash_map::RandomState;
+        use std::hash::Hasher;
+
+        std::thread_local! {
+            // Like the std `RandomState`, the keys are drawn from the OS entropy once per thread,
+            // then the first one is incremented for every new instance.
+            static KEYS: Cell<(u64, u64)> = Cell::new(random_keys());
+        }
+
+        /// Draw random keys from the OS entropy, through the keys of the std `RandomState`.
+        fn random_keys() -> (u64, u64) {
+            let state = RandomState::new();
+            let mut h = state.build_hasher();
+
+            h.write_u64(0);
+            let k0 = h.finish();
+            h.write_u64(1);
+            let k1 = h.finish();
+
+            (k0, k1)
+        }
+
+        fn next_keys() -> (u64, u64) {
+            KEYS.with(|keys| {
+                let (k0, k1) = keys.get();
+                keys.set((k0.wrapping_add(1), k1));
+                (k0, k1)
+            })
+        }
+
+        /// A builder of `t1ha` hashers with a random seed, which resists HashDoS attacks.
+        ///
+        /// The seed is drawn from the OS entropy once per thread, and perturbed for every instance.
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// use t1ha::T1haRandomHashMap;
+        ///
+        /// let mut map = T1haRandomHashMap::default();
+        /// map.insert(1, "one");
+        /// ```
+        #[derive(Clone)]
+        pub struct T1haRandomState {
+            seed: u64,
+        }
+
+        impl T1haRandomState {
+            /// Create a builder with a random seed.
+            #[inline]
+            pub fn new() -> Self {
+                let (k0, _) = next_keys();
+
+                T1haRandomState { seed: k0 }
+            }
+        }
+
+        impl Default for T1haRandomState {
+            #[inline]
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl fmt::Debug for T1haRandomState {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_struct("T1haRandomState").finish_non_exhaustive()
+            }
+        }
+
+        impl BuildHasher for T1haRandomState {
+            type Hasher = T1haHasher;
+
+            #[inline]
+            fn build_hasher(&self) -> T1haHasher {
+                T1haHasher::with_seed(self.seed)
+            }
+        }
+
+        /// A builder of `t1ha2` hashers with random seeds, which resists HashDoS attacks.
+        ///
+        /// The seeds are drawn from the OS entropy once per thread, and perturbed for every instance.
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// use t1ha::T1ha2RandomHashMap;
+        ///
+        /// let mut map = T1ha2RandomHashMap::default();
+        /// map.insert(1, "one");
+        /// ```
+        #[derive(Clone)]
+        pub struct T1ha2RandomState {
+            key: T1ha2Key,
+        }
+
+        impl T1ha2RandomState {
+            /// Create a builder with random seeds.
+            #[inline]
+            pub fn new() -> Self {
+                let (k0, k1) = next_keys();
+
+                T1ha2RandomState {
+                    key: T1ha2Key::new(k0, k1),
+                }
+            }
+        }
+
+        impl Default for T1ha2RandomState {
+            #[inline]
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl fmt::Debug for T1ha2RandomState {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_struct("T1ha2RandomState").finish_non_exhaustive()
+            }
+        }
+
+        impl BuildHasher for T1ha2RandomState {
+            type Hasher = T1ha2Hasher;
+
+            #[inline]
+            fn build_hasher(&self) -> T1ha2Hasher {
+                self.key.hasher()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::hash::{BuildHasher, Hash};
+
+    use super::*;
+
+    #[test]
+    fn test_seeded_state() {
+        let _lock = crate::t1ha0::lock_selection();
+        let state = T1haSeededState::new(42);
+
+        assert_eq!(
+            state.hash_one("hello"),
+            T1haSeededState::new(42).hash_one("hello")
+        );
+        assert_ne!(
+            state.hash_one("hello"),
+            T1haSeededState::new(43).hash_one("hello")
+        );
+
+        assert_ne!(state.hash_one(()), 42);
+
+        let key = T1ha2Key::new(1, 2);
+        let mut h = T1ha2Hasher::with_seeds(1, 2);
+        "hello".hash(&mut h);
+
+        assert_eq!(key.hash_one("hello"), h.finish());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_random_state() {
+        let _lock = crate::t1ha0::lock_selection();
+        let state = T1haRandomState::new();
+
+        assert_eq!(state.hash_one("hello"), state.hash_one("hello"));
+        assert_eq!(state.hash_one("hello"), state.clone().hash_one("hello"));
+        assert_ne!(
+            state.hash_one("hello"),
+            T1haRandomState::new().hash_one("hello")
+        );
+
+        assert_ne!(state.hash_one(()), state.seed);
+
+        let state = T1ha2RandomState::new();
+
+        assert_eq!(state.hash_one("hello"), state.hash_one("hello"));
+        assert_ne!(
+            state.hash_one("hello"),
+            T1ha2RandomState::new().hash_one("hello")
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_random_keys() {
+        let keys = std::thread::spawn(next_keys).join().unwrap();
+
+        assert_ne!(keys, next_keys());
+    }
+}
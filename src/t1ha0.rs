@@ -28,9 +28,20 @@
 //!      bit faster (less overhead) than via a PLT thru the DSO boundary.
 //!    - GNU Indirect functions (see below) don't supported by environment
 //!      and calling by t1ha0_funcptr is not available and/or expensive.
+//!
+//!  In Rust, the implementation chosen by t1ha0() could be inspected with
+//!  [`selected()`], pinned with [`force()`], or overridden with the
+//!  `T1HA0_IMPL` environment variable (see [`T1HA0_IMPL_ENV`]).
 #![allow(clippy::cast_ptr_alignment, clippy::many_single_char_names)]
 
-use crate::{bits::*, nightly::*};
+use core::fmt;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::{
+    bits::*,
+    declared::{BlockState, Declared, LengthMismatch},
+    nightly::*,
+};
 
 // 32-bit 'magic' primes
 const PRIME32_0: u32 = 0x92D7_8269;
@@ -42,13 +53,13 @@ const PRIME32_5: u32 = 0xCA2D_A6FB;
 const PRIME32_6: u32 = 0xC4BB_3575;
 
 /// The little-endian variant for 32-bit CPU.
-#[cfg(feature = "unaligned_access")]
+#[cfg(unaligned_access)]
 pub fn t1ha0_32le(data: &[u8], seed: u64) -> u64 {
     unsafe { t1h0_body::<LittenEndianUnaligned<u32>>(data, seed) }
 }
 
 /// The little-endian variant for 32-bit CPU.
-#[cfg(not(feature = "unaligned_access"))]
+#[cfg(not(unaligned_access))]
 pub fn t1ha0_32le(data: &[u8], seed: u64) -> u64 {
     if !aligned_to::<u32, _>(data.as_ptr()) {
         unsafe { t1h0_body::<LittenEndianUnaligned<u32>>(data, seed) }
@@ -58,13 +69,13 @@ pub fn t1ha0_32le(data: &[u8], seed: u64) -> u64 {
 }
 
 /// The big-endian variant for 32-bit CPU.
-#[cfg(feature = "unaligned_access")]
+#[cfg(unaligned_access)]
 pub fn t1ha0_32be(data: &[u8], seed: u64) -> u64 {
     unsafe { t1h0_body::<BigEndianUnaligned<u32>>(data, seed) }
 }
 
 /// The big-endian variant for 32-bit CPU.
-#[cfg(not(feature = "unaligned_access"))]
+#[cfg(not(unaligned_access))]
 pub fn t1ha0_32be(data: &[u8], seed: u64) -> u64 {
     if !aligned_to::<u32, _>(data.as_ptr()) {
         unsafe { t1h0_body::<BigEndianUnaligned<u32>>(data, seed) }
@@ -73,272 +84,702 @@ pub fn t1ha0_32be(data: &[u8], seed: u64) -> u64 {
     }
 }
 
+/// A streaming `t1ha0_32` hasher for the data with a length known in advance.
+///
+/// `t1ha0_32` mixes the length of the data into its initial state,
+/// so the length must be declared when the hasher is created.
+///
+/// # Example
+///
+/// ```
+/// use t1ha::{t1ha0_32le, T1ha0_32Hasher};
+///
+/// let mut h = T1ha0_32Hasher::with_len(42, 11);
+/// h.update(b"hello ").unwrap();
+/// h.update(b"world").unwrap();
+///
+/// assert_eq!(h.finish(), Ok(t1ha0_32le(b"hello world", 42)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct T1ha0_32Hasher(Declared<State32>);
+
+impl T1ha0_32Hasher {
+    /// Create a hasher of `len` bytes, which returns the same value as `t1ha0_32le()`.
+    pub fn with_len(seed: u64, len: u64) -> Self {
+        T1ha0_32Hasher(Declared::new(seed, len, false))
+    }
+
+    /// Create a hasher of `len` bytes, which returns the same value as `t1ha0_32be()`.
+    pub fn with_len_be(seed: u64, len: u64) -> Self {
+        T1ha0_32Hasher(Declared::new(seed, len, true))
+    }
+
+    /// Returns the declared length of the data.
+    pub fn len(&self) -> u64 {
+        self.0.len()
+    }
+
+    /// Returns `true` if the declared length of the data is zero.
+    pub fn is_empty(&self) -> bool {
+        self.0.len() == 0
+    }
+
+    /// Hash the next chunk of the data.
+    ///
+    /// Returns an error if the data goes beyond the declared length, then the chunk is not hashed,
+    /// and [`finish()`](T1ha0_32Hasher::finish) will fail too.
+    pub fn update(&mut self, data: &[u8]) -> Result<(), LengthMismatch> {
+        self.0.update(data)
+    }
+
+    /// Returns the hash value, or an error if the length of the hashed data differs from the declared length.
+    pub fn finish(&self) -> Result<u64, LengthMismatch> {
+        self.0.finish()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct State32 {
+    a: u32,
+    b: u32,
+    c: u32,
+    d: u32,
+}
+
+impl BlockState for State32 {
+    type Word = u32;
+
+    #[inline(always)]
+    fn new(seed: u64, len: u64) -> Self {
+        let len = len as u32;
+        let a = rot32(len, 17).wrapping_add(seed as u32);
+        let b = len ^ ((seed >> 32) as u32);
+
+        State32 {
+            a,
+            b,
+            c: !a,
+            d: rot32(b, 5),
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn update<T: MemoryModel<Item = u32>>(&mut self, v: *const u32) {
+        let w0 = T::fetch(v.offset(0));
+        let w1 = T::fetch(v.offset(1));
+        let w2 = T::fetch(v.offset(2));
+        let w3 = T::fetch(v.offset(3));
+
+        let d13 = w1.wrapping_add(rot32(w3.wrapping_add(self.d), 17));
+        let c02 = w0 ^ rot32(w2.wrapping_add(self.c), 11);
+        self.d ^= rot32(self.a.wrapping_add(w0), 3);
+        self.c ^= rot32(self.b.wrapping_add(w1), 7);
+        self.b = PRIME32_1.wrapping_mul(c02.wrapping_add(w3));
+        self.a = PRIME32_0.wrapping_mul(d13 ^ w2);
+    }
+
+    /// Update the state with all the 16-byte blocks of the data, returns the tail.
+    #[inline(always)]
+    unsafe fn blocks<'a, T: MemoryModel<Item = u32>>(&mut self, data: &'a [u8]) -> &'a [u8] {
+        let p = data.as_ptr();
+        let detent = p as usize + data.len() - 15;
+        let mut v = p as *const u32;
+
+        while likely((v as usize) < detent) {
+            let w = v;
+            v = v.add(4);
+            prefetch(v);
+
+            self.update::<T>(w);
+        }
+
+        &data[(v as usize) - (p as usize)..]
+    }
+
+    #[inline(always)]
+    fn squash(&mut self) {
+        self.c = self.c.wrapping_add(self.a);
+        self.d = self.d.wrapping_add(self.b);
+        self.a ^= rot32(self.c, 16)
+            .wrapping_add(self.d)
+            .wrapping_mul(PRIME32_6);
+        self.b ^= self
+            .c
+            .wrapping_add(rot32(self.d, 16))
+            .wrapping_mul(PRIME32_5);
+    }
+
+    #[inline(always)]
+    unsafe fn tail<T: MemoryModel<Item = u32>>(&self, v: *const u32, len: usize) -> u64 {
+        let mut a = self.a;
+        let mut b = self.b;
+
+        match len {
+            13..=16 => {
+                mixup32(&mut a, &mut b, T::fetch(v.offset(0)), PRIME32_4);
+                mixup32(&mut b, &mut a, T::fetch(v.offset(1)), PRIME32_3);
+                mixup32(&mut a, &mut b, T::fetch(v.offset(2)), PRIME32_2);
+                mixup32(
+                    &mut b,
+                    &mut a,
+                    T::tail(v.offset(3), len as isize),
+                    PRIME32_1,
+                );
+                final32(a, b)
+            }
+            9..=12 => {
+                mixup32(&mut b, &mut a, T::fetch(v.offset(0)), PRIME32_3);
+                mixup32(&mut a, &mut b, T::fetch(v.offset(1)), PRIME32_2);
+                mixup32(
+                    &mut b,
+                    &mut a,
+                    T::tail(v.offset(2), len as isize),
+                    PRIME32_1,
+                );
+                final32(a, b)
+            }
+            5..=8 => {
+                mixup32(&mut a, &mut b, T::fetch(v.offset(0)), PRIME32_2);
+                mixup32(
+                    &mut b,
+                    &mut a,
+                    T::tail(v.offset(1), len as isize),
+                    PRIME32_1,
+                );
+                final32(a, b)
+            }
+            1..=4 => {
+                mixup32(
+                    &mut b,
+                    &mut a,
+                    T::tail(v.offset(0), len as isize),
+                    PRIME32_1,
+                );
+                final32(a, b)
+            }
+            0 => final32(a, b),
+            _ => unreachable!(),
+        }
+    }
+}
+
 #[inline(always)]
-unsafe fn t1h0_body<T>(data: &[u8], seed: u64) -> u64
+unsafe fn t1h0_body<T>(mut data: &[u8], seed: u64) -> u64
 where
     T: MemoryModel<Item = u32>,
 {
-    let mut len = data.len();
-    let mut a = rot32(len as u32, 17).wrapping_add(seed as u32);
-    let mut b = (len as u32) ^ ((seed >> 32) as u32);
-    let mut v = data.as_ptr() as *const u32;
+    let len = data.len();
+    let mut state = State32::new(seed, len as u64);
 
     if unlikely(len > 16) {
-        let mut c = !a;
-        let mut d = rot32(b, 5);
-        let detent = (v as *const u8).offset(len as isize - 15) as usize;
+        data = state.blocks::<T>(data);
+        state.squash();
+    }
 
-        while likely((v as usize) < detent) {
-            let w0 = T::fetch(v.offset(0));
-            let w1 = T::fetch(v.offset(1));
-            let w2 = T::fetch(v.offset(2));
-            let w3 = T::fetch(v.offset(3));
-            v = v.add(4);
-            prefetch(v);
+    state.tail::<T>(data.as_ptr() as *const u32, data.len())
+}
+
+/// The implementations which could be chosen by `t1ha0()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum T1ha0Impl {
+    /// The 64-bit portable variant, `t1ha1_le()` or `t1ha1_be()` depending on the target endianness.
+    Portable64 = 1,
+    /// The little-endian variant for 32-bit CPU, `t1ha0_32le()`.
+    Portable32Le,
+    /// The big-endian variant for 32-bit CPU, `t1ha0_32be()`.
+    Portable32Be,
+    /// x86 with AES-NI without AVX extensions.
+    Ia32Aes,
+    /// x86 with AES-NI and AVX extensions.
+    Ia32AesAvx,
+    /// x86 with AES-NI and AVX2 extensions.
+    Ia32AesAvx2,
+}
+
+/// The environment variable which overrides the implementation chosen by `t1ha0()`.
+///
+/// It holds the [name](T1ha0Impl::name) of an implementation, and is read once,
+/// when `t1ha0()` resolves its implementation. Unknown or unsupported names are ignored.
+pub const T1HA0_IMPL_ENV: &str = "T1HA0_IMPL";
+
+impl T1ha0Impl {
+    /// All the implementations, from the most portable to the fastest one.
+    pub const ALL: [T1ha0Impl; 6] = [
+        T1ha0Impl::Portable64,
+        T1ha0Impl::Portable32Le,
+        T1ha0Impl::Portable32Be,
+        T1ha0Impl::Ia32Aes,
+        T1ha0Impl::Ia32AesAvx,
+        T1ha0Impl::Ia32AesAvx2,
+    ];
 
-            let d13 = w1.wrapping_add(rot32(w3.wrapping_add(d), 17));
-            let c02 = w0 ^ rot32(w2.wrapping_add(c), 11);
-            d ^= rot32(a.wrapping_add(w0), 3);
-            c ^= rot32(b.wrapping_add(w1), 7);
-            b = PRIME32_1.wrapping_mul(c02.wrapping_add(w3));
-            a = PRIME32_0.wrapping_mul(d13 ^ w2);
+    /// Returns the name of the implementation.
+    pub fn name(self) -> &'static str {
+        match self {
+            T1ha0Impl::Portable64 => "portable64",
+            T1ha0Impl::Portable32Le => "portable32le",
+            T1ha0Impl::Portable32Be => "portable32be",
+            T1ha0Impl::Ia32Aes => "ia32aes",
+            T1ha0Impl::Ia32AesAvx => "ia32aes_avx",
+            T1ha0Impl::Ia32AesAvx2 => "ia32aes_avx2",
         }
+    }
+
+    /// Returns the implementation with the given name.
+    pub fn from_name(name: &str) -> Option<T1ha0Impl> {
+        T1ha0Impl::ALL
+            .iter()
+            .copied()
+            .find(|imp| imp.name() == name)
+    }
 
-        c = c.wrapping_add(a);
-        d = d.wrapping_add(b);
-        a ^= rot32(c, 16).wrapping_add(d).wrapping_mul(PRIME32_6);
-        b ^= c.wrapping_add(rot32(d, 16)).wrapping_mul(PRIME32_5);
-        len &= 15;
-    }
-
-    match len {
-        13..=16 => {
-            mixup32(&mut a, &mut b, T::fetch(v.offset(0)), PRIME32_4);
-            mixup32(&mut b, &mut a, T::fetch(v.offset(1)), PRIME32_3);
-            mixup32(&mut a, &mut b, T::fetch(v.offset(2)), PRIME32_2);
-            mixup32(
-                &mut b,
-                &mut a,
-                T::tail(v.offset(3), len as isize),
-                PRIME32_1,
-            );
-            final32(a, b)
+    /// Returns `true` if the implementation is supported by the current CPU.
+    pub fn is_available(self) -> bool {
+        match self {
+            T1ha0Impl::Portable64 | T1ha0Impl::Portable32Le | T1ha0Impl::Portable32Be => true,
+            T1ha0Impl::Ia32Aes => cpu::has_aes(),
+            T1ha0Impl::Ia32AesAvx => cpu::has_aes() && cpu::has_avx(),
+            T1ha0Impl::Ia32AesAvx2 => cpu::has_aes() && cpu::has_avx2(),
         }
-        9..=12 => {
-            mixup32(&mut b, &mut a, T::fetch(v.offset(0)), PRIME32_3);
-            mixup32(&mut a, &mut b, T::fetch(v.offset(1)), PRIME32_2);
-            mixup32(
-                &mut b,
-                &mut a,
-                T::tail(v.offset(2), len as isize),
-                PRIME32_1,
-            );
-            final32(a, b)
+    }
+
+    fn from_u8(v: u8) -> Option<T1ha0Impl> {
+        T1ha0Impl::ALL.get(usize::from(v).wrapping_sub(1)).copied()
+    }
+
+    /// Hash the data with the implementation.
+    ///
+    /// # Safety
+    ///
+    /// The implementation must be supported by the current CPU.
+    #[inline(always)]
+    pub(crate) unsafe fn hash_unchecked(self, data: &[u8], seed: u64) -> u64 {
+        match self {
+            T1ha0Impl::Portable64 => crate::t1ha1(data, seed),
+            T1ha0Impl::Portable32Le => t1ha0_32le(data, seed),
+            T1ha0Impl::Portable32Be => t1ha0_32be(data, seed),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            T1ha0Impl::Ia32Aes => crate::t1ha0_aes::ia32aes_noavx(data, seed),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            T1ha0Impl::Ia32AesAvx => crate::t1ha0_aes::ia32aes_avx(data, seed),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            T1ha0Impl::Ia32AesAvx2 => crate::t1ha0_aes::ia32aes_avx2(data, seed),
+            #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+            _ => unreachable!(),
         }
-        5..=8 => {
-            mixup32(&mut a, &mut b, T::fetch(v.offset(0)), PRIME32_2);
-            mixup32(
-                &mut b,
-                &mut a,
-                T::tail(v.offset(1), len as isize),
-                PRIME32_1,
-            );
-            final32(a, b)
+    }
+}
+
+impl fmt::Display for T1ha0Impl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The error returned by [`force()`] when the implementation is not supported by the current CPU.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Unsupported(pub T1ha0Impl);
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "t1ha0 implementation `{}` is not supported", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Unsupported {}
+
+/// The implementation used by `t1ha0()`, or zero if it has not been resolved yet.
+static SELECTED: AtomicU8 = AtomicU8::new(0);
+
+/// Returns the implementation used by `t1ha0()`.
+#[inline]
+pub fn selected() -> T1ha0Impl {
+    match T1ha0Impl::from_u8(SELECTED.load(Ordering::Relaxed)) {
+        Some(imp) => imp,
+        None => {
+            let imp = resolve();
+
+            // Only store the resolved implementation if it is still unresolved, so a concurrent `force()` wins.
+            match SELECTED.compare_exchange(0, imp as u8, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => imp,
+                Err(current) => T1ha0Impl::from_u8(current).unwrap_or(imp),
+            }
         }
-        1..=4 => {
-            mixup32(
-                &mut b,
-                &mut a,
-                T::tail(v.offset(0), len as isize),
-                PRIME32_1,
-            );
-            final32(a, b)
+    }
+}
+
+/// Returns the implementations supported by the current CPU.
+pub fn available() -> impl Iterator<Item = T1ha0Impl> {
+    T1ha0Impl::ALL
+        .iter()
+        .copied()
+        .filter(|imp| imp.is_available())
+}
+
+/// Force `t1ha0()` to use the given implementation.
+pub fn force(imp: T1ha0Impl) -> Result<(), Unsupported> {
+    if imp.is_available() {
+        SELECTED.store(imp as u8, Ordering::Relaxed);
+
+        Ok(())
+    } else {
+        Err(Unsupported(imp))
+    }
+}
+
+/// Drop the implementation forced by [`force()`], `t1ha0()` will resolve it again.
+pub fn reset() {
+    SELECTED.store(0, Ordering::Relaxed);
+}
+
+fn resolve() -> T1ha0Impl {
+    cfg_if! {
+        if #[cfg(feature = "std")] {
+            resolve_with(std::env::var(T1HA0_IMPL_ENV).ok().as_deref())
+        } else {
+            resolve_with(None)
         }
-        0 => final32(a, b),
-        _ => unreachable!(),
     }
 }
 
+/// Resolve the implementation, the one named by `T1HA0_IMPL` wins if it is available.
+fn resolve_with(name: Option<&str>) -> T1ha0Impl {
+    if let Some(imp) = name
+        .and_then(|name| T1ha0Impl::from_name(name.trim()))
+        .filter(|imp| imp.is_available())
+    {
+        return imp;
+    }
+
+    if cfg!(feature = "runtime_select") {
+        if T1ha0Impl::Ia32AesAvx2.is_available() {
+            return T1ha0Impl::Ia32AesAvx2;
+        } else if T1ha0Impl::Ia32AesAvx.is_available() {
+            return T1ha0Impl::Ia32AesAvx;
+        } else if T1ha0Impl::Ia32Aes.is_available() {
+            return T1ha0Impl::Ia32Aes;
+        }
+    }
+
+    fallback()
+}
+
+fn fallback() -> T1ha0Impl {
+    if cfg!(target_pointer_width = "64") {
+        T1ha0Impl::Portable64
+    } else if cfg!(target_endian = "little") {
+        T1ha0Impl::Portable32Le
+    } else {
+        T1ha0Impl::Portable32Be
+    }
+}
+
+/// CPU features detection with `CPUID`, which works without `std`.
+pub(crate) mod cpu {
+    cfg_if! {
+        if #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), not(target_env = "sgx"), not(miri)))] {
+            use core::sync::atomic::{AtomicU8, Ordering};
+
+            #[cfg(target_arch = "x86")]
+            use core::arch::x86::{__cpuid, __cpuid_count, _xgetbv};
+            #[cfg(target_arch = "x86_64")]
+            use core::arch::x86_64::{__cpuid, __cpuid_count, _xgetbv};
+
+            const AES: u8 = 1 << 0;
+            const AVX: u8 = 1 << 1;
+            const AVX2: u8 = 1 << 2;
+            const DETECTED: u8 = 1 << 7;
+
+            /// The detected features, or zero if they have not been detected yet.
+            static FEATURES: AtomicU8 = AtomicU8::new(0);
+
+            pub fn has_aes() -> bool {
+                features() & AES != 0
+            }
+
+            pub fn has_avx() -> bool {
+                features() & AVX != 0
+            }
+
+            pub fn has_avx2() -> bool {
+                features() & AVX2 != 0
+            }
+
+            fn features() -> u8 {
+                let mut features = FEATURES.load(Ordering::Relaxed);
+
+                if features == 0 {
+                    features = unsafe { detect() } | DETECTED;
+                    FEATURES.store(features, Ordering::Relaxed);
+                }
+
+                features
+            }
+
+            // Every i586+ target has `CPUID`, so it is not probed for on 32-bit x86.
+            unsafe fn detect() -> u8 {
+                let max_leaf = __cpuid(0).eax;
+
+                if max_leaf < 1 {
+                    return 0;
+                }
+
+                let ecx = __cpuid(1).ecx;
+                let mut features = 0;
+
+                if ecx & (1 << 25) != 0 {
+                    features |= AES;
+                }
+
+                // AVX also requires the OS to save the YMM registers on context switches.
+                let osxsave = ecx & (1 << 27) != 0;
+                let avx = ecx & (1 << 28) != 0;
+
+                if osxsave && avx && xcr0() & 0b110 == 0b110 {
+                    features |= AVX;
+
+                    if max_leaf >= 7 && __cpuid_count(7, 0).ebx & (1 << 5) != 0 {
+                        features |= AVX2;
+                    }
+                }
+
+                features
+            }
+
+            #[target_feature(enable = "xsave")]
+            unsafe fn xcr0() -> u64 {
+                _xgetbv(0)
+            }
+        } else if #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), not(miri)))] {
+            pub fn has_aes() -> bool {
+                cfg!(target_feature = "aes")
+            }
+
+            pub fn has_avx() -> bool {
+                cfg!(target_feature = "avx")
+            }
+
+            pub fn has_avx2() -> bool {
+                cfg!(all(target_feature = "avx", target_feature = "avx2"))
+            }
+        } else {
+            // Miri only checks the portable implementations.
+            pub fn has_aes() -> bool {
+                false
+            }
+
+            pub fn has_avx() -> bool {
+                false
+            }
+
+            pub fn has_avx2() -> bool {
+                false
+            }
+        }
+    }
+}
+
+/// Serializes the tests which force the implementation with the tests which depend on it.
+#[cfg(test)]
+pub(crate) fn lock_selection() -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    LOCK.lock().unwrap_or_else(|err| err.into_inner())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::selfcheck::selfcheck;
-
-    const T1HA_REFVAL_32LE: [u64; 81] = [
-        0,
-        0xC92229C10FAEA50E,
-        0x3DF1354B0DFDC443,
-        0x968F016D60417BB3,
-        0x85AAFB50C6DA770F,
-        0x66CCE3BB6842C7D6,
-        0xDDAA39C11537C226,
-        0x35958D281F0C9C8C,
-        0x8C5D64B091DE608E,
-        0x4094DF680D39786B,
-        0x1014F4AA2A2EDF4D,
-        0x39D21891615AA310,
-        0x7EF51F67C398C7C4,
-        0x06163990DDBF319D,
-        0xE229CAA00C8D6F3F,
-        0xD2240B4B0D54E0F5,
-        0xEA2E7E905DDEAF94,
-        0x8D4F8A887183A5CE,
-        0x44337F9A63C5820C,
-        0x94938D1E86A9B797,
-        0x96E9CABA5CA210CC,
-        0x6EFBB9CC9E8F7708,
-        0x3D12EA0282FB8BBC,
-        0x5DA781EE205A2C48,
-        0xFA4A51A12677FE12,
-        0x81D5F04E20660B28,
-        0x57258D043BCD3841,
-        0x5C9BEB62059C1ED2,
-        0x57A02162F9034B33,
-        0xBA2A13E457CE19B8,
-        0xE593263BF9451F3A,
-        0x0BC1175539606BC5,
-        0xA3E2929E9C5F289F,
-        0x86BDBD06835E35F7,
-        0xA180950AB48BAADC,
-        0x7812C994D9924028,
-        0x308366011415F46B,
-        0x77FE9A9991C5F959,
-        0x925C340B70B0B1E3,
-        0xCD9C5BA4C41E2E10,
-        0x7CC4E7758B94CD93,
-        0x898B235962EA4625,
-        0xD7E3E5BF22893286,
-        0x396F4CDD33056C64,
-        0x740AB2E32F17CD9F,
-        0x60D12FF9CD15B321,
-        0xBEE3A6C9903A81D8,
-        0xB47040913B33C35E,
-        0x19EE8C2ACC013CFF,
-        0x5DEC94C5783B55C4,
-        0x78DC122D562C5F1D,
-        0x6520F008DA1C181E,
-        0x77CAF155A36EBF7C,
-        0x0A09E02BDB883CA6,
-        0xFD5D9ADA7E3FB895,
-        0xC6F5FDD9EEAB83B5,
-        0x84589BB29F52A92A,
-        0x9B2517F13F8E9814,
-        0x6F752AF6A52E31EC,
-        0x8E717799E324CE8A,
-        0x84D90AEF39262D58,
-        0x79C27B13FC28944D,
-        0xE6D6DF6438E0044A,
-        0x51B603E400D79CA4,
-        0x6A902B28C588B390,
-        0x8D7F8DE9E6CB1D83,
-        0xCF1A4DC11CA7F044,
-        0xEF02E43C366786F1,
-        0x89915BCDBCFBE30F,
-        0x5928B306F1A9CC7F,
-        0xA8B59092996851C5,
-        0x22050A20427E8B25,
-        0x6E6D64018941E7EE,
-        0x9798C898B81AE846,
-        0x80EF218CDC30124A,
-        0xFCE45E60D55B0284,
-        0x4010E735D3147C35,
-        0xEB647D999FD8DC7E,
-        0xD3544DCAB14FE907,
-        0xB588B27D8438700C,
-        0xA49EBFC43E057A4C,
-    ];
-
-    const T1HA_REFVAL_32BE: [u64; 81] = [
-        0,
-        0xC92229C10FAEA50E,
-        0x0FE212630DD87E0F,
-        0x968F016D60417BB3,
-        0xE6B12B2C889913AB,
-        0xAA3787887A9DA368,
-        0x06EE7202D53CEF39,
-        0x6149AFB2C296664B,
-        0x86C893210F9A5805,
-        0x8379E5DA988AA04C,
-        0x24763AA7CE411A60,
-        0x9CF9C64B395A4CF8,
-        0xFFC192C338DDE904,
-        0x094575BAB319E5F5,
-        0xBBBACFE7728C6511,
-        0x36B8C3CEBE4EF409,
-        0xAA0BA8A3397BA4D0,
-        0xF9F85CF7124EE653,
-        0x3ADF4F7DF2A887AE,
-        0xAA2A0F5964AA9A7A,
-        0xF18B563F42D36EB8,
-        0x034366CEF8334F5C,
-        0xAE2E85180E330E5F,
-        0xA5CE9FBFDF5C65B8,
-        0x5E509F25A9CA9B0B,
-        0xE30D1358C2013BD2,
-        0xBB3A04D5EB8111FE,
-        0xB04234E82A15A28D,
-        0x87426A56D0EA0E2F,
-        0x095086668E07F9F8,
-        0xF4CD3A43B6A6AEA5,
-        0x73F9B9B674D472A6,
-        0x558344229A1E4DCF,
-        0x0AD4C95B2279181A,
-        0x5E3D19D80821CA6B,
-        0x652492D25BEBA258,
-        0xEFA84B02EAB849B1,
-        0x81AD2D253059AC2C,
-        0x1400CCB0DFB2F457,
-        0x5688DC72A839860E,
-        0x67CC130E0FD1B0A7,
-        0x0A851E3A94E21E69,
-        0x2EA0000B6A073907,
-        0xAE9776FF9BF1D02E,
-        0xC0A96B66B160631C,
-        0xA93341DE4ED7C8F0,
-        0x6FBADD8F5B85E141,
-        0xB7D295F1C21E0CBA,
-        0x6D6114591B8E434F,
-        0xF5B6939B63D97BE7,
-        0x3C80D5053F0E5DB4,
-        0xAC520ACC6B73F62D,
-        0xD1051F5841CF3966,
-        0x62245AEA644AE760,
-        0x0CD56BE15497C62D,
-        0x5BB93435C4988FB6,
-        0x5FADB88EB18DB512,
-        0xC897CAE2242475CC,
-        0xF1A094EF846DC9BB,
-        0x2B1D8B24924F79B6,
-        0xC6DF0C0E8456EB53,
-        0xE6A40128303A9B9C,
-        0x64D37AF5EFFA7BD9,
-        0x90FEB70A5AE2A598,
-        0xEC3BA5F126D9FF4B,
-        0x3121C8EC3AC51B29,
-        0x3B41C4D422166EC1,
-        0xB4878DDCBF48ED76,
-        0x5CB850D77CB762E4,
-        0x9A27A43CC1DD171F,
-        0x2FDFFC6F99CB424A,
-        0xF54A57E09FDEA7BB,
-        0x5F78E5EE2CAB7039,
-        0xB8BA95883DB31CBA,
-        0x131C61EB84AF86C3,
-        0x84B1F64E9C613DA7,
-        0xE94C1888C0C37C02,
-        0xEA08F8BFB2039CDE,
-        0xCCC6D04D243EC753,
-        0x8977D105298B0629,
-        0x7AAA976494A5905E,
-    ];
+    use crate::selfcheck;
 
     #[test]
     fn test_t1ha0_32le() {
-        selfcheck(t1ha0_32le, &T1HA_REFVAL_32LE[..])
+        selfcheck::t1ha0_32le().unwrap()
+    }
+
+    #[test]
+    fn test_memory_models() {
+        use selfcheck::{check_at_offset, T1HA_REFVAL_32BE, T1HA_REFVAL_32LE};
+
+        for offset in 0..8 {
+            check_at_offset(
+                "t1ha0_32le",
+                offset,
+                |data, seed| unsafe { t1h0_body::<LittenEndianUnaligned<u32>>(data, seed) },
+                &T1HA_REFVAL_32LE,
+            )
+            .unwrap();
+            check_at_offset(
+                "t1ha0_32be",
+                offset,
+                |data, seed| unsafe { t1h0_body::<BigEndianUnaligned<u32>>(data, seed) },
+                &T1HA_REFVAL_32BE,
+            )
+            .unwrap();
+        }
+
+        for offset in (0..8).step_by(4) {
+            check_at_offset(
+                "t1ha0_32le",
+                offset,
+                |data, seed| unsafe { t1h0_body::<LittenEndianAligned<u32>>(data, seed) },
+                &T1HA_REFVAL_32LE,
+            )
+            .unwrap();
+            check_at_offset(
+                "t1ha0_32be",
+                offset,
+                |data, seed| unsafe { t1h0_body::<BigEndianAligned<u32>>(data, seed) },
+                &T1HA_REFVAL_32BE,
+            )
+            .unwrap();
+        }
+
+        for offset in 0..8 {
+            check_at_offset("t1ha0_32le", offset, t1ha0_32le, &T1HA_REFVAL_32LE).unwrap();
+            check_at_offset("t1ha0_32be", offset, t1ha0_32be, &T1HA_REFVAL_32BE).unwrap();
+        }
     }
 
     #[test]
     fn test_t1ha0_32be() {
-        selfcheck(t1ha0_32be, &T1HA_REFVAL_32BE[..])
+        selfcheck::t1ha0_32be().unwrap()
+    }
+
+    #[test]
+    fn test_t1ha0_32_hasher() {
+        let data = (0..128)
+            .map(|i| (i * 7) as u8)
+            .collect::<std::vec::Vec<_>>();
+
+        for len in 0..data.len() {
+            for &chunk in &[1, 3, 4, 15, 16, 17, 50, 128] {
+                let data = &data[..len];
+
+                let mut le = T1ha0_32Hasher::with_len(42, len as u64);
+                let mut be = T1ha0_32Hasher::with_len_be(42, len as u64);
+                for chunk in data.chunks(chunk) {
+                    le.update(chunk).unwrap();
+                    be.update(chunk).unwrap();
+                }
+
+                assert_eq!(le.finish(), Ok(t1ha0_32le(data, 42)));
+                assert_eq!(be.finish(), Ok(t1ha0_32be(data, 42)));
+            }
+        }
+
+        let mut h = T1ha0_32Hasher::with_len(0, 11);
+        h.update(b"hello").unwrap();
+        assert_eq!(
+            h.finish(),
+            Err(LengthMismatch {
+                expected: 11,
+                actual: 5
+            })
+        );
+        assert_eq!(
+            h.update(b" world!"),
+            Err(LengthMismatch {
+                expected: 11,
+                actual: 12
+            })
+        );
+    }
+
+    #[test]
+    fn test_impl_name() {
+        for &imp in &T1ha0Impl::ALL {
+            assert_eq!(T1ha0Impl::from_name(imp.name()), Some(imp));
+        }
+
+        assert_eq!(T1ha0Impl::from_name("unknown"), None);
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[test]
+    fn test_cpu_features() {
+        use std::is_x86_feature_detected;
+
+        // Miri only checks the portable implementations.
+        let detected = |feature: bool| feature && !cfg!(miri);
+
+        assert_eq!(cpu::has_aes(), detected(is_x86_feature_detected!("aes")));
+        assert_eq!(cpu::has_avx(), detected(is_x86_feature_detected!("avx")));
+        assert_eq!(cpu::has_avx2(), detected(is_x86_feature_detected!("avx2")));
+    }
+
+    #[test]
+    fn test_force() {
+        let _lock = lock_selection();
+
+        assert!(selected().is_available());
+        assert!(available().any(|imp| imp == T1ha0Impl::Portable64));
+
+        let data = b"hello world";
+
+        for imp in available() {
+            force(imp).unwrap();
+            assert_eq!(selected(), imp);
+            assert_eq!(crate::t1ha0(data, 0), unsafe {
+                imp.hash_unchecked(data, 0)
+            });
+        }
+
+        force(T1ha0Impl::Portable32Le).unwrap();
+        assert_eq!(crate::t1ha0(data, 0), t1ha0_32le(data, 0));
+
+        if let Some(imp) = T1ha0Impl::ALL.iter().find(|imp| !imp.is_available()) {
+            assert_eq!(force(*imp), Err(Unsupported(*imp)));
+            assert_eq!(selected(), T1ha0Impl::Portable32Le);
+        }
+
+        reset();
+        assert!(selected().is_available());
+    }
+
+    #[test]
+    fn test_resolve_with() {
+        let default = resolve_with(None);
+
+        assert!(default.is_available());
+        assert_eq!(resolve_with(Some("portable32be")), T1ha0Impl::Portable32Be);
+        assert_eq!(
+            resolve_with(Some(" portable32le\n")),
+            T1ha0Impl::Portable32Le
+        );
+        assert_eq!(resolve_with(Some("unknown")), default);
+
+        if let Some(imp) = T1ha0Impl::ALL.iter().find(|imp| !imp.is_available()) {
+            assert_eq!(resolve_with(Some(imp.name())), default);
+        }
+    }
+
+    /// Runs itself in a child process with the environment variable set,
+    /// because `set_var()` is not safe while the other tests are running.
+    ///
+    /// Miri can't spawn processes, `test_resolve_with()` checks the same rules in process.
+    #[cfg(all(feature = "std", not(miri)))]
+    #[test]
+    fn test_impl_env() {
+        const CHILD_ENV: &str = "T1HA0_TEST_IMPL_ENV_CHILD";
+
+        if std::env::var_os(CHILD_ENV).is_some() {
+            assert_eq!(selected(), T1ha0Impl::Portable32Be);
+            return;
+        }
+
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "t1ha0::tests::test_impl_env", "--test-threads=1"])
+            .env(CHILD_ENV, "1")
+            .env(T1HA0_IMPL_ENV, "portable32be")
+            .output()
+            .unwrap();
+
+        assert!(
+            output.status.success(),
+            "{}",
+            std::string::String::from_utf8_lossy(&output.stdout)
+        );
     }
 }
@@ -0,0 +1,432 @@
+//! The software emulation of the AES-NI variants of `t1ha0`.
+//!
+//! The AES rounds and the SSE arithmetic are emulated on plain integers,
+//! so the results are bit-identical to the `t1ha0_ia32aes_*()` functions on any target,
+//! for example to recompute a hash produced on an x86 server from an ARM laptop.
+//! It is much slower than the hardware implementations.
+
+use crate::bits::*;
+
+/// The AES S-box, built at compile time.
+const SBOX: [u8; 256] = sbox();
+
+/// The inverse of the AES S-box.
+const INV_SBOX: [u8; 256] = inv_sbox(&SBOX);
+
+const fn rotl8(x: u8, n: u32) -> u8 {
+    x.rotate_left(n)
+}
+
+const fn sbox() -> [u8; 256] {
+    let mut sbox = [0u8; 256];
+    let mut p: u8 = 1;
+    let mut q: u8 = 1;
+
+    // `p` walks over all the non-zero elements of GF(2^8) by multiplying with 3,
+    // while `q` is its multiplicative inverse, divided by 3 at every step.
+    loop {
+        p = p ^ (p << 1) ^ if p & 0x80 != 0 { 0x1B } else { 0 };
+
+        q ^= q << 1;
+        q ^= q << 2;
+        q ^= q << 4;
+        if q & 0x80 != 0 {
+            q ^= 0x09;
+        }
+
+        let x = q ^ rotl8(q, 1) ^ rotl8(q, 2) ^ rotl8(q, 3) ^ rotl8(q, 4);
+        sbox[p as usize] = x ^ 0x63;
+
+        if p == 1 {
+            break;
+        }
+    }
+
+    sbox[0] = 0x63;
+    sbox
+}
+
+const fn inv_sbox(sbox: &[u8; 256]) -> [u8; 256] {
+    let mut inv = [0u8; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        inv[sbox[i] as usize] = i as u8;
+        i += 1;
+    }
+
+    inv
+}
+
+/// Multiply by `x` in GF(2^8).
+#[inline(always)]
+fn xtime(x: u8) -> u8 {
+    (x << 1) ^ if x & 0x80 != 0 { 0x1B } else { 0 }
+}
+
+/// Multiply in GF(2^8).
+#[inline(always)]
+fn gmul(mut a: u8, mut b: u8) -> u8 {
+    let mut r = 0;
+
+    while b != 0 {
+        if b & 1 != 0 {
+            r ^= a;
+        }
+        a = xtime(a);
+        b >>= 1;
+    }
+
+    r
+}
+
+/// A 128-bit vector, as the `__m128i` with two 64-bit lanes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct V128 {
+    lo: u64,
+    hi: u64,
+}
+
+impl V128 {
+    /// The same as `_mm_set_epi64x(hi, lo)`.
+    #[inline(always)]
+    const fn new(hi: u64, lo: u64) -> Self {
+        V128 { lo, hi }
+    }
+
+    /// The same as `_mm_loadu_si128()`.
+    #[inline(always)]
+    fn load(data: &[u8]) -> Self {
+        V128 {
+            lo: fetch_le(&data[..8]),
+            hi: fetch_le(&data[8..16]),
+        }
+    }
+
+    #[inline(always)]
+    fn to_bytes(self) -> [u8; 16] {
+        let mut b = [0; 16];
+        b[..8].copy_from_slice(&self.lo.to_le_bytes());
+        b[8..].copy_from_slice(&self.hi.to_le_bytes());
+        b
+    }
+
+    #[inline(always)]
+    fn from_bytes(b: [u8; 16]) -> Self {
+        V128 {
+            lo: fetch_le(&b[..8]),
+            hi: fetch_le(&b[8..]),
+        }
+    }
+
+    /// The same as `_mm_add_epi64()`.
+    #[inline(always)]
+    fn add(self, other: Self) -> Self {
+        V128 {
+            lo: self.lo.wrapping_add(other.lo),
+            hi: self.hi.wrapping_add(other.hi),
+        }
+    }
+
+    /// The same as `_mm_sub_epi64()`.
+    #[inline(always)]
+    fn sub(self, other: Self) -> Self {
+        V128 {
+            lo: self.lo.wrapping_sub(other.lo),
+            hi: self.hi.wrapping_sub(other.hi),
+        }
+    }
+
+    /// The same as `_mm_xor_si128()`.
+    #[inline(always)]
+    fn xor(self, other: Self) -> Self {
+        V128 {
+            lo: self.lo ^ other.lo,
+            hi: self.hi ^ other.hi,
+        }
+    }
+
+    /// The same as `_mm_aesenc_si128()`: `ShiftRows`, `SubBytes`, `MixColumns` and `AddRoundKey`.
+    fn aesenc(self, key: Self) -> Self {
+        let s = self.to_bytes();
+        let mut t = [0u8; 16];
+
+        for c in 0..4 {
+            for r in 0..4 {
+                t[r + 4 * c] = SBOX[s[r + 4 * ((c + r) % 4)] as usize];
+            }
+        }
+
+        let mut m = [0u8; 16];
+
+        for c in 0..4 {
+            let [a0, a1, a2, a3] = [t[4 * c], t[4 * c + 1], t[4 * c + 2], t[4 * c + 3]];
+
+            m[4 * c] = xtime(a0) ^ xtime(a1) ^ a1 ^ a2 ^ a3;
+            m[4 * c + 1] = a0 ^ xtime(a1) ^ xtime(a2) ^ a2 ^ a3;
+            m[4 * c + 2] = a0 ^ a1 ^ xtime(a2) ^ xtime(a3) ^ a3;
+            m[4 * c + 3] = xtime(a0) ^ a0 ^ a1 ^ a2 ^ xtime(a3);
+        }
+
+        V128::from_bytes(m).xor(key)
+    }
+
+    /// The same as `_mm_aesdec_si128()`: `InvShiftRows`, `InvSubBytes`, `InvMixColumns` and `AddRoundKey`.
+    fn aesdec(self, key: Self) -> Self {
+        let s = self.to_bytes();
+        let mut t = [0u8; 16];
+
+        for c in 0..4 {
+            for r in 0..4 {
+                t[r + 4 * c] = INV_SBOX[s[r + 4 * ((c + 4 - r) % 4)] as usize];
+            }
+        }
+
+        let mut m = [0u8; 16];
+
+        for c in 0..4 {
+            let [a0, a1, a2, a3] = [t[4 * c], t[4 * c + 1], t[4 * c + 2], t[4 * c + 3]];
+
+            m[4 * c] = gmul(a0, 14) ^ gmul(a1, 11) ^ gmul(a2, 13) ^ gmul(a3, 9);
+            m[4 * c + 1] = gmul(a0, 9) ^ gmul(a1, 14) ^ gmul(a2, 11) ^ gmul(a3, 13);
+            m[4 * c + 2] = gmul(a0, 13) ^ gmul(a1, 9) ^ gmul(a2, 14) ^ gmul(a3, 11);
+            m[4 * c + 3] = gmul(a0, 11) ^ gmul(a1, 13) ^ gmul(a2, 9) ^ gmul(a3, 14);
+        }
+
+        V128::from_bytes(m).xor(key)
+    }
+}
+
+#[inline(always)]
+fn fetch_le(b: &[u8]) -> u64 {
+    let mut buf = [0; 8];
+    buf[..b.len()].copy_from_slice(b);
+    u64::from_le_bytes(buf)
+}
+
+/// The software emulation of `t1ha0_ia32aes_noavx()` and `t1ha0_ia32aes_avx()`.
+///
+/// It returns the same values on any target.
+pub fn t1ha0_ia32aes_portable(data: &[u8], seed: u64) -> u64 {
+    let len = data.len();
+    let mut a = seed;
+    let mut b = len as u64;
+    let mut v = data;
+
+    if len > 32 {
+        let mut x = V128::new(a, b);
+        let mut y = x.aesenc(V128::new(PRIME_5, PRIME_6));
+
+        while v.len() >= 128 {
+            let v0 = V128::load(&v[0..]);
+            let v1 = V128::load(&v[16..]);
+            let v2 = V128::load(&v[32..]);
+            let v3 = V128::load(&v[48..]);
+            let v4 = V128::load(&v[64..]);
+            let v5 = V128::load(&v[80..]);
+            let v6 = V128::load(&v[96..]);
+            let v7 = V128::load(&v[112..]);
+            v = &v[128..];
+
+            let v0y = v0.aesenc(y);
+            let v2x6 = v2.aesenc(x.xor(v6));
+            let v45_67 = v4.aesenc(v5).xor(v6.add(v7));
+
+            let v0y7_1 = v7.sub(v0y).aesdec(v1);
+            let v2x6_3 = v2x6.aesenc(v3);
+
+            x = v45_67.aesenc(x.add(y));
+            y = v2x6_3.aesenc(v0y7_1.xor(v5));
+        }
+
+        if (len & 64) != 0 {
+            let v0y = y.add(V128::load(&v[0..]));
+            let v1x = x.sub(V128::load(&v[16..]));
+            x = x.aesdec(v0y);
+            y = y.aesdec(v1x);
+
+            let v2y = y.add(V128::load(&v[32..]));
+            let v3x = x.sub(V128::load(&v[48..]));
+            x = x.aesdec(v2y);
+            y = y.aesdec(v3x);
+
+            v = &v[64..];
+        }
+
+        if (len & 32) != 0 {
+            let v0y = y.add(V128::load(&v[0..]));
+            let v1x = x.sub(V128::load(&v[16..]));
+            x = x.aesdec(v0y);
+            y = y.aesdec(v1x);
+
+            v = &v[32..];
+        }
+
+        if (len & 16) != 0 {
+            y = x.add(y);
+            x = x.aesdec(V128::load(v));
+            v = &v[16..];
+        }
+
+        x = x.aesdec(y.aesenc(x)).add(y);
+
+        a = x.lo;
+        b = x.hi;
+    }
+
+    tail(v, a, b)
+}
+
+/// The software emulation of `t1ha0_ia32aes_avx2()`.
+///
+/// It returns the same values on any target.
+pub fn t1ha0_ia32aes_avx2_portable(data: &[u8], seed: u64) -> u64 {
+    let len = data.len();
+    let mut a = seed;
+    let mut b = len as u64;
+    let mut v = data;
+
+    if len > 32 {
+        let mut x = V128::new(a, b);
+        let mut y = x.aesenc(V128::new(PRIME_0, PRIME_1));
+        let (mut blocks, tail) = v.split_at(len & !15);
+
+        v = tail;
+
+        if (len & 16) != 0 {
+            x = x.add(V128::load(blocks));
+            y = x.aesenc(y);
+            blocks = &blocks[16..];
+        }
+
+        let mut salt = y;
+
+        while blocks.len() >= 128 {
+            let mut t = V128::load(&blocks[0..]).aesenc(salt);
+            t = t.aesdec(V128::load(&blocks[16..]));
+            t = t.aesdec(V128::load(&blocks[32..]));
+            t = t.aesdec(V128::load(&blocks[48..]));
+            t = t.aesdec(V128::load(&blocks[64..]));
+            t = t.aesdec(V128::load(&blocks[80..]));
+            t = t.aesdec(V128::load(&blocks[96..]));
+            t = t.aesdec(V128::load(&blocks[112..]));
+
+            blocks = &blocks[128..];
+
+            salt = salt.add(V128::new(PRIME_5, PRIME_6));
+            t = x.aesenc(t);
+            x = y.add(x);
+            y = t;
+        }
+
+        while !blocks.is_empty() {
+            let v0y = y.add(V128::load(&blocks[0..]));
+            let v1x = x.sub(V128::load(&blocks[16..]));
+
+            blocks = &blocks[32..];
+
+            x = x.aesdec(v0y);
+            y = y.aesdec(v1x);
+        }
+
+        x = x.aesdec(y.aesenc(x)).add(y);
+
+        a = x.lo;
+        b = x.hi;
+    }
+
+    tail(v, a, b)
+}
+
+/// Hash the last (up to 32) bytes, the same as the tail of the AES-NI variants.
+#[inline(always)]
+fn tail(v: &[u8], mut a: u64, mut b: u64) -> u64 {
+    match v.len() {
+        25..=32 => {
+            mixup64(&mut a, &mut b, fetch_le(&v[0..8]), PRIME_4);
+            mixup64(&mut b, &mut a, fetch_le(&v[8..16]), PRIME_3);
+            mixup64(&mut a, &mut b, fetch_le(&v[16..24]), PRIME_2);
+            mixup64(&mut b, &mut a, fetch_le(&v[24..]), PRIME_1);
+            final64(a, b)
+        }
+        17..=24 => {
+            mixup64(&mut b, &mut a, fetch_le(&v[0..8]), PRIME_3);
+            mixup64(&mut a, &mut b, fetch_le(&v[8..16]), PRIME_2);
+            mixup64(&mut b, &mut a, fetch_le(&v[16..]), PRIME_1);
+            final64(a, b)
+        }
+        9..=16 => {
+            mixup64(&mut a, &mut b, fetch_le(&v[0..8]), PRIME_2);
+            mixup64(&mut b, &mut a, fetch_le(&v[8..]), PRIME_1);
+            final64(a, b)
+        }
+        1..=8 => {
+            mixup64(&mut b, &mut a, fetch_le(v), PRIME_1);
+            final64(a, b)
+        }
+        0 => final64(a, b),
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selfcheck;
+
+    #[test]
+    fn test_sbox() {
+        assert_eq!(SBOX[0x00], 0x63);
+        assert_eq!(SBOX[0x01], 0x7C);
+        assert_eq!(SBOX[0x53], 0xED);
+        assert_eq!(SBOX[0xFF], 0x16);
+        assert_eq!(INV_SBOX[0x63], 0x00);
+        assert_eq!(INV_SBOX[0xED], 0x53);
+    }
+
+    #[test]
+    fn test_ia32aes_portable() {
+        selfcheck::t1ha0_ia32aes_portable().unwrap()
+    }
+
+    #[test]
+    fn test_ia32aes_avx2_portable() {
+        selfcheck::t1ha0_ia32aes_avx2_portable().unwrap()
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[test]
+    fn test_aes_rounds() {
+        #[cfg(target_arch = "x86")]
+        use core::arch::x86::*;
+        #[cfg(target_arch = "x86_64")]
+        use core::arch::x86_64::*;
+
+        #[target_feature(enable = "aes")]
+        unsafe fn rounds(s: V128, k: V128) -> (V128, V128) {
+            let s = _mm_set_epi64x(s.hi as i64, s.lo as i64);
+            let k = _mm_set_epi64x(k.hi as i64, k.lo as i64);
+            let [enc, dec]: [[u64; 2]; 2] =
+                core::mem::transmute([_mm_aesenc_si128(s, k), _mm_aesdec_si128(s, k)]);
+
+            (V128::new(enc[1], enc[0]), V128::new(dec[1], dec[0]))
+        }
+
+        if !std::is_x86_feature_detected!("aes") {
+            return;
+        }
+
+        let mut x = PRIME_0;
+        let mut next = || {
+            x = mix64(x, PRIME_1).wrapping_add(PRIME_2);
+            x
+        };
+
+        for _ in 0..1000 {
+            let s = V128::new(next(), next());
+            let k = V128::new(next(), next());
+
+            assert_eq!(unsafe { rounds(s, k) }, (s.aesenc(k), s.aesdec(k)));
+        }
+    }
+}
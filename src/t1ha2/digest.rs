@@ -0,0 +1,318 @@
+//! [RustCrypto `digest`](https://docs.rs/digest) traits for `t1ha2`.
+//!
+//! `T1ha2Stream64` and `T1ha2Stream128` wrap the `T1ha2Hasher` stream hasher, in constant memory.
+//! With the `digest_atonce` feature, `T1ha2Atonce64` and `T1ha2Atonce128` match `t1ha2_atonce()`
+//! and `t1ha2_atonce128()` instead, so they buffer the whole input.
+//!
+//! `t1ha2` is not a cryptographic hash function,
+//! so the `HashMarker` trait is deliberately not implemented, and the `Digest` trait is unavailable.
+
+#[cfg(feature = "digest_atonce")]
+use std::vec::Vec;
+
+use ::digest::{
+    consts::{U16, U8},
+    FixedOutput, Output, OutputSizeUser, Reset, Update,
+};
+
+use super::T1ha2Hasher;
+#[cfg(feature = "digest_atonce")]
+use super::{t1ha2_atonce, t1ha2_atonce128};
+
+macro_rules! t1ha2_stream_digest {
+    ($(#[$attr:meta])* $name:ident, $size:ty, $finish:ident) => {
+        $(#[$attr])*
+        #[derive(Clone, Debug, Default)]
+        pub struct $name {
+            hasher: T1ha2Hasher,
+        }
+
+        impl $name {
+            /// Create a hasher with the seeds.
+            pub fn with_seeds(seed_x: u64, seed_y: u64) -> Self {
+                $name {
+                    hasher: T1ha2Hasher::with_seeds(seed_x, seed_y),
+                }
+            }
+        }
+
+        impl OutputSizeUser for $name {
+            type OutputSize = $size;
+        }
+
+        impl Update for $name {
+            fn update(&mut self, data: &[u8]) {
+                self.hasher.update(data);
+            }
+        }
+
+        impl FixedOutput for $name {
+            fn finalize_into(self, out: &mut Output<Self>) {
+                out.copy_from_slice(&self.hasher.$finish().to_le_bytes());
+            }
+        }
+
+        impl Reset for $name {
+            fn reset(&mut self) {
+                self.hasher.reset();
+            }
+        }
+    };
+}
+
+t1ha2_stream_digest!(
+    /// The 64-bit `t1ha2` stream hasher for the `digest` traits, in constant memory.
+    ///
+    /// The output is the little-endian bytes of `T1ha2Hasher::finish()`,
+    /// which differs from `t1ha2_atonce()` of the same data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use digest::{FixedOutput, Update};
+    /// use t1ha::{T1ha2Hasher, T1ha2Stream64};
+    ///
+    /// let mut h = T1ha2Stream64::default();
+    /// h.update(b"hello ");
+    /// h.update(b"world");
+    ///
+    /// let mut expected = T1ha2Hasher::default();
+    /// expected.update(b"hello world");
+    ///
+    /// assert_eq!(h.finalize_fixed()[..], expected.finish().to_le_bytes());
+    /// ```
+    T1ha2Stream64,
+    U8,
+    finish
+);
+
+t1ha2_stream_digest!(
+    /// The 128-bit `t1ha2` stream hasher for the `digest` traits, in constant memory.
+    ///
+    /// The output is the little-endian bytes of `T1ha2Hasher::finish128()`,
+    /// which differs from `t1ha2_atonce128()` of the same data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use digest::{FixedOutput, Update};
+    /// use t1ha::{T1ha2Hasher, T1ha2Stream128};
+    ///
+    /// let mut h = T1ha2Stream128::with_seeds(42, 42);
+    /// h.update(b"hello ");
+    /// h.update(b"world");
+    ///
+    /// let mut expected = T1ha2Hasher::with_seeds(42, 42);
+    /// expected.update(b"hello world");
+    ///
+    /// assert_eq!(h.finalize_fixed()[..], expected.finish128().to_le_bytes());
+    /// ```
+    T1ha2Stream128,
+    U16,
+    finish128
+);
+
+macro_rules! t1ha2_atonce_digest {
+    ($(#[$attr:meta])* $name:ident, $size:ty, $hash:ident) => {
+        $(#[$attr])*
+        #[cfg(feature = "digest_atonce")]
+        #[derive(Clone, Debug, Default)]
+        pub struct $name {
+            seed: u64,
+            data: Vec<u8>,
+        }
+
+        #[cfg(feature = "digest_atonce")]
+        impl $name {
+            /// Create a hasher with the seed.
+            pub fn with_seed(seed: u64) -> Self {
+                $name {
+                    seed,
+                    data: Vec::new(),
+                }
+            }
+        }
+
+        #[cfg(feature = "digest_atonce")]
+        impl OutputSizeUser for $name {
+            type OutputSize = $size;
+        }
+
+        #[cfg(feature = "digest_atonce")]
+        impl Update for $name {
+            fn update(&mut self, data: &[u8]) {
+                self.data.extend_from_slice(data);
+            }
+        }
+
+        #[cfg(feature = "digest_atonce")]
+        impl FixedOutput for $name {
+            fn finalize_into(self, out: &mut Output<Self>) {
+                out.copy_from_slice(&$hash(&self.data, self.seed).to_le_bytes());
+            }
+        }
+
+        #[cfg(feature = "digest_atonce")]
+        impl Reset for $name {
+            fn reset(&mut self) {
+                self.data.clear();
+            }
+        }
+    };
+}
+
+t1ha2_atonce_digest!(
+    /// The 64-bit `t1ha2` hash function for the `digest` traits, matching `t1ha2_atonce()`.
+    ///
+    /// **The whole input is buffered in memory until it is finalized**, because `t1ha2_atonce()`
+    /// needs its length up front, so it is only available with the `digest_atonce` feature.
+    /// Use [`T1ha2Stream64`] to hash large or unbounded inputs.
+    ///
+    /// The output is the little-endian bytes of `t1ha2_atonce()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use digest::{FixedOutput, Update};
+    /// use t1ha::{t1ha2_atonce, T1ha2Atonce64};
+    ///
+    /// let mut h = T1ha2Atonce64::default();
+    /// h.update(b"hello ");
+    /// h.update(b"world");
+    ///
+    /// assert_eq!(h.finalize_fixed()[..], t1ha2_atonce(b"hello world", 0).to_le_bytes());
+    /// ```
+    T1ha2Atonce64,
+    U8,
+    t1ha2_atonce
+);
+
+t1ha2_atonce_digest!(
+    /// The 128-bit `t1ha2` hash function for the `digest` traits, matching `t1ha2_atonce128()`.
+    ///
+    /// **The whole input is buffered in memory until it is finalized**, because `t1ha2_atonce128()`
+    /// needs its length up front, so it is only available with the `digest_atonce` feature.
+    /// Use [`T1ha2Stream128`] to hash large or unbounded inputs.
+    ///
+    /// The output is the little-endian bytes of `t1ha2_atonce128()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use digest::{FixedOutput, Update};
+    /// use t1ha::{t1ha2_atonce128, T1ha2Atonce128};
+    ///
+    /// let mut h = T1ha2Atonce128::with_seed(42);
+    /// h.update(b"hello ");
+    /// h.update(b"world");
+    ///
+    /// assert_eq!(h.finalize_fixed()[..], t1ha2_atonce128(b"hello world", 42).to_le_bytes());
+    /// ```
+    T1ha2Atonce128,
+    U16,
+    t1ha2_atonce128
+);
+
+#[cfg(test)]
+mod tests {
+    use ::digest::{FixedOutput, Reset, Update};
+
+    use super::*;
+    use crate::selfcheck;
+
+    #[cfg(feature = "digest_atonce")]
+    #[test]
+    fn test_t1ha2_atonce64() {
+        selfcheck::check(
+            "T1ha2Atonce64",
+            |data, seed| {
+                let mut h = T1ha2Atonce64::with_seed(seed);
+                for chunk in data.chunks(7) {
+                    h.update(chunk);
+                }
+                let mut out = [0; 8];
+                out.copy_from_slice(&h.finalize_fixed());
+                u64::from_le_bytes(out)
+            },
+            &selfcheck::T1HA_REFVAL_2ATONCE,
+        )
+        .unwrap()
+    }
+
+    #[cfg(feature = "digest_atonce")]
+    #[test]
+    fn test_t1ha2_atonce128() {
+        selfcheck::check(
+            "T1ha2Atonce128",
+            |data, seed| {
+                let mut h = T1ha2Atonce128::with_seed(seed);
+                for chunk in data.chunks(7) {
+                    h.update(chunk);
+                }
+                let mut out = [0; 16];
+                out.copy_from_slice(&h.finalize_fixed());
+                u128::from_le_bytes(out) as u64
+            },
+            &selfcheck::T1HA_REFVAL_2ATONCE128,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_t1ha2_stream() {
+        selfcheck::check(
+            "T1ha2Stream64",
+            |data, seed| {
+                let mut h = T1ha2Stream64::with_seeds(seed, seed);
+                for chunk in data.chunks(7) {
+                    h.update(chunk);
+                }
+                let mut out = [0; 8];
+                out.copy_from_slice(&h.finalize_fixed());
+                u64::from_le_bytes(out)
+            },
+            &selfcheck::T1HA_REFVAL_2STREAM,
+        )
+        .unwrap();
+        selfcheck::check(
+            "T1ha2Stream128",
+            |data, seed| {
+                let mut h = T1ha2Stream128::with_seeds(seed, seed);
+                for chunk in data.chunks(7) {
+                    h.update(chunk);
+                }
+                let mut out = [0; 16];
+                out.copy_from_slice(&h.finalize_fixed());
+                u128::from_le_bytes(out) as u64
+            },
+            &selfcheck::T1HA_REFVAL_2STREAM128,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_reset() {
+        #[cfg(feature = "digest_atonce")]
+        {
+            let mut h = T1ha2Atonce128::with_seed(42);
+            h.update(b"garbage");
+            Reset::reset(&mut h);
+            h.update(b"hello world");
+
+            assert_eq!(
+                h.finalize_fixed()[..],
+                t1ha2_atonce128(b"hello world", 42).to_le_bytes()
+            );
+        }
+
+        let mut h = T1ha2Stream128::with_seeds(42, 42);
+        h.update(b"garbage");
+        Reset::reset(&mut h);
+        h.update(b"hello world");
+
+        let mut expected = T1ha2Hasher::with_seeds(42, 42);
+        expected.update(b"hello world");
+
+        assert_eq!(h.finalize_fixed()[..], expected.finish128().to_le_bytes());
+    }
+}
@@ -0,0 +1,413 @@
+//! `std::io` adaptors which compute a `t1ha2` digest of the data passing through.
+
+use std::boxed::Box;
+use std::io::{self, BufRead, Read, Write};
+use std::vec;
+
+use super::T1ha2Hasher;
+
+#[cfg(feature = "async")]
+mod async_io;
+
+#[cfg(feature = "async")]
+pub use self::async_io::{hash_stream, try_hash_stream};
+
+impl Write for T1ha2Hasher {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.update(buf);
+
+        Ok(())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A reader which updates a `t1ha2` hasher with the data read from the underlying reader.
+///
+/// With the `async` feature, it also implements `AsyncRead` of `tokio` and `futures-io`.
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+/// use t1ha::{t1ha2::HashingReader, T1ha2Hasher};
+///
+/// let mut reader = HashingReader::new(&b"hello world"[..]);
+/// let mut copy = Vec::new();
+///
+/// io::copy(&mut reader, &mut copy).unwrap();
+///
+/// let mut h = T1ha2Hasher::default();
+/// h.update(b"hello world");
+///
+/// assert_eq!(copy, b"hello world");
+/// assert_eq!(reader.finish128(), h.finish128());
+/// ```
+///
+/// `BufRead` is implemented with its own buffer, like `BufReader`, which is allocated by the first
+/// `fill_buf()` and filled with `read()` of the underlying reader. Only the consumed data is hashed.
+#[derive(Clone, Debug)]
+pub struct HashingReader<R> {
+    inner: R,
+    hasher: T1ha2Hasher,
+    buf: Box<[u8]>,
+    /// The number of bytes of `buf` which have been consumed.
+    pos: usize,
+    /// The number of bytes of `buf` which have been filled by the last `fill_buf()`.
+    filled: usize,
+}
+
+/// The size of the buffer of `HashingReader`, the same as the default of `BufReader`.
+const HASHING_READER_BUFFER_SIZE: usize = 8 * 1024;
+
+impl<R> HashingReader<R> {
+    /// Wrap the reader with a default `t1ha2` hasher.
+    pub fn new(inner: R) -> Self {
+        Self::with_hasher(inner, T1ha2Hasher::default())
+    }
+
+    /// Wrap the reader with the hasher.
+    pub fn with_hasher(inner: R, hasher: T1ha2Hasher) -> Self {
+        HashingReader {
+            inner,
+            hasher,
+            buf: Box::default(),
+            pos: 0,
+            filled: 0,
+        }
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// The data read directly from the underlying reader is not hashed,
+    /// and it does not include the data which is buffered and not consumed yet.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Gets a reference to the hasher.
+    pub fn hasher(&self) -> &T1ha2Hasher {
+        &self.hasher
+    }
+
+    /// Returns the 64-bit hash value of the data read so far.
+    pub fn finish(&self) -> u64 {
+        self.hasher.finish()
+    }
+
+    /// Returns the 128-bit hash value of the data read so far.
+    pub fn finish128(&self) -> u128 {
+        self.hasher.finish128()
+    }
+
+    /// Unwraps this `HashingReader`, returning the underlying reader and the hasher.
+    ///
+    /// The data which is buffered and not consumed yet is lost.
+    pub fn into_parts(self) -> (R, T1ha2Hasher) {
+        (self.inner, self.hasher)
+    }
+
+    /// Unwraps this `HashingReader`, returning the underlying reader.
+    ///
+    /// The data which is buffered and not consumed yet is lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Copy and consume the buffered data, which is left by `fill_buf()`, into `buf`.
+    fn read_buffered(&mut self, buf: &mut [u8]) -> usize {
+        let n = buf.len().min(self.filled - self.pos);
+
+        buf[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.consume_buffered(n);
+
+        n
+    }
+
+    fn consume_buffered(&mut self, amt: usize) {
+        let end = self.filled.min(self.pos + amt);
+
+        self.hasher.update(&self.buf[self.pos..end]);
+        self.pos = end;
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos < self.filled {
+            return Ok(self.read_buffered(buf));
+        }
+
+        let n = self.inner.read(buf)?;
+
+        self.hasher.update(&buf[..n]);
+
+        Ok(n)
+    }
+}
+
+impl<R: Read> BufRead for HashingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos >= self.filled {
+            if self.buf.is_empty() {
+                self.buf = vec![0; HASHING_READER_BUFFER_SIZE].into_boxed_slice();
+            }
+
+            self.filled = self.inner.read(&mut self.buf)?;
+            self.pos = 0;
+        }
+
+        Ok(&self.buf[self.pos..self.filled])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.consume_buffered(amt)
+    }
+}
+
+/// A writer which updates a `t1ha2` hasher with the data written to the underlying writer.
+///
+/// With the `async` feature, it also implements `AsyncWrite` of `tokio` and `futures-io`.
+///
+/// # Example
+///
+/// ```
+/// use std::io::Write;
+/// use t1ha::{t1ha2::HashingWriter, T1ha2Hasher};
+///
+/// let mut writer = HashingWriter::new(Vec::new());
+///
+/// writer.write_all(b"hello world").unwrap();
+///
+/// let mut h = T1ha2Hasher::default();
+/// h.update(b"hello world");
+///
+/// assert_eq!(writer.finish128(), h.finish128());
+/// assert_eq!(writer.into_inner(), b"hello world");
+/// ```
+#[derive(Clone, Debug)]
+pub struct HashingWriter<W> {
+    inner: W,
+    hasher: T1ha2Hasher,
+}
+
+impl<W> HashingWriter<W> {
+    /// Wrap the writer with a default `t1ha2` hasher.
+    pub fn new(inner: W) -> Self {
+        Self::with_hasher(inner, T1ha2Hasher::default())
+    }
+
+    /// Wrap the writer with the hasher.
+    pub fn with_hasher(inner: W, hasher: T1ha2Hasher) -> Self {
+        HashingWriter { inner, hasher }
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    ///
+    /// The data written directly to the underlying writer is not hashed.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Gets a reference to the hasher.
+    pub fn hasher(&self) -> &T1ha2Hasher {
+        &self.hasher
+    }
+
+    /// Returns the 64-bit hash value of the data written so far.
+    pub fn finish(&self) -> u64 {
+        self.hasher.finish()
+    }
+
+    /// Returns the 128-bit hash value of the data written so far.
+    pub fn finish128(&self) -> u128 {
+        self.hasher.finish128()
+    }
+
+    /// Unwraps this `HashingWriter`, returning the underlying writer and the hasher.
+    pub fn into_parts(self) -> (W, T1ha2Hasher) {
+        (self.inner, self.hasher)
+    }
+
+    /// Unwraps this `HashingWriter`, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+
+        self.hasher.update(&buf[..n]);
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Returns the 128-bit `t1ha2` hash value of all the data read from the reader.
+///
+/// The result is the same as `T1ha2Hasher::finish128()` of a default hasher updated with the data.
+///
+/// # Example
+///
+/// ```
+/// use t1ha::{t1ha2::hash_reader, T1ha2Hasher};
+///
+/// let mut h = T1ha2Hasher::default();
+/// h.update(b"hello world");
+///
+/// assert_eq!(hash_reader(&b"hello world"[..]).unwrap(), h.finish128());
+/// ```
+pub fn hash_reader<R: Read>(mut reader: R) -> io::Result<u128> {
+    let mut hasher = T1ha2Hasher::default();
+
+    io::copy(&mut reader, &mut hasher)?;
+
+    Ok(hasher.finish128())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, BufRead, BufReader, Read, Write};
+    use std::vec::Vec;
+
+    use super::*;
+
+    fn expected(data: &[u8]) -> u128 {
+        let mut h = T1ha2Hasher::default();
+        h.update(data);
+        h.finish128()
+    }
+
+    #[test]
+    fn test_io_write() {
+        let data = (0..1000).map(|i| i as u8).collect::<Vec<_>>();
+        let mut h = T1ha2Hasher::default();
+
+        for chunk in data.chunks(7) {
+            h.write_all(chunk).unwrap();
+        }
+
+        assert_eq!(h.finish128(), expected(&data));
+    }
+
+    #[test]
+    fn test_hashing_reader() {
+        let data = (0..1000).map(|i| i as u8).collect::<Vec<_>>();
+
+        let mut reader = HashingReader::new(&data[..]);
+        let mut copy = Vec::new();
+        assert_eq!(io::copy(&mut reader, &mut copy).unwrap(), data.len() as u64);
+        assert_eq!(copy, data);
+        assert_eq!(reader.finish128(), expected(&data));
+
+        let mut reader = HashingReader::new(BufReader::with_capacity(13, &data[..]));
+        let mut lines = 0;
+        let mut buf = Vec::new();
+        while reader.read_until(b'\n', &mut buf).unwrap() > 0 {
+            lines += 1;
+        }
+        assert_eq!(lines, 5);
+        assert_eq!(buf, data);
+        assert_eq!(reader.finish128(), expected(&data));
+
+        let mut reader = HashingReader::new(&data[..]);
+        let mut buf = [0; 100];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(reader.finish128(), expected(&data[..100]));
+
+        let mut reader = HashingReader::new(&data[..]);
+        assert_eq!(reader.fill_buf().unwrap(), &data[..]);
+        reader.consume(10);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf[..], data[10..110]);
+        assert_eq!(reader.finish128(), expected(&data[..110]));
+    }
+
+    /// A reader which returns its data from the first `read()`, and fails afterwards.
+    struct FailingReader<'a> {
+        data: &'a [u8],
+        filled: bool,
+    }
+
+    impl Read for FailingReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.filled {
+                Err(io::Error::other("read called again"))
+            } else {
+                self.filled = true;
+                buf[..self.data.len()].copy_from_slice(self.data);
+
+                Ok(self.data.len())
+            }
+        }
+    }
+
+    #[test]
+    fn test_hashing_reader_consume() {
+        let data = b"hello world";
+        let mut reader = HashingReader::new(FailingReader {
+            data,
+            filled: false,
+        });
+
+        assert_eq!(reader.fill_buf().unwrap(), data);
+        reader.consume(0);
+        reader.consume(5);
+        reader.consume(6);
+        assert_eq!(reader.finish128(), expected(data));
+
+        assert!(reader.fill_buf().is_err());
+        reader.consume(0);
+        assert_eq!(reader.finish128(), expected(data));
+    }
+
+    #[test]
+    fn test_hashing_writer() {
+        let data = (0..1000).map(|i| i as u8).collect::<Vec<_>>();
+
+        let mut writer = HashingWriter::new(Vec::new());
+        assert_eq!(
+            io::copy(&mut &data[..], &mut writer).unwrap(),
+            data.len() as u64
+        );
+        writer.flush().unwrap();
+        assert_eq!(writer.finish128(), expected(&data));
+        assert_eq!(writer.into_inner(), data);
+    }
+
+    #[test]
+    fn test_hash_reader() {
+        let data = (0..1000).map(|i| i as u8).collect::<Vec<_>>();
+
+        for len in [0, 1, 31, 32, 33, 1000] {
+            assert_eq!(hash_reader(&data[..len]).unwrap(), expected(&data[..len]));
+        }
+    }
+}
@@ -0,0 +1,310 @@
+//! Asynchronous adaptors of `tokio` and `futures-io` which compute a `t1ha2` digest of the data passing through.
+
+use core::future::poll_fn;
+use core::pin::{pin, Pin};
+use core::task::{Context, Poll};
+use std::io;
+
+use futures_core::Stream;
+
+use super::{HashingReader, HashingWriter};
+use crate::t1ha2::T1ha2Hasher;
+
+impl tokio::io::AsyncWrite for T1ha2Hasher {
+    fn poll_write(self: Pin<&mut Self>, _cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.get_mut().update(buf);
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl futures_io::AsyncWrite for T1ha2Hasher {
+    fn poll_write(self: Pin<&mut Self>, _cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.get_mut().update(buf);
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R> tokio::io::AsyncRead for HashingReader<R>
+where
+    R: tokio::io::AsyncRead + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut tokio::io::ReadBuf,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if this.pos < this.filled {
+            let n = buf.remaining().min(this.filled - this.pos);
+
+            buf.put_slice(&this.buf[this.pos..this.pos + n]);
+            this.consume_buffered(n);
+
+            return Poll::Ready(Ok(()));
+        }
+
+        let filled = buf.filled().len();
+
+        match Pin::new(&mut this.inner).poll_read(cx, buf) {
+            Poll::Ready(Ok(())) => {
+                this.hasher.update(&buf.filled()[filled..]);
+
+                Poll::Ready(Ok(()))
+            }
+            res => res,
+        }
+    }
+}
+
+impl<R> futures_io::AsyncRead for HashingReader<R>
+where
+    R: futures_io::AsyncRead + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if this.pos < this.filled {
+            return Poll::Ready(Ok(this.read_buffered(buf)));
+        }
+
+        match Pin::new(&mut this.inner).poll_read(cx, buf) {
+            Poll::Ready(Ok(n)) => {
+                this.hasher.update(&buf[..n]);
+
+                Poll::Ready(Ok(n))
+            }
+            res => res,
+        }
+    }
+}
+
+impl<W> tokio::io::AsyncWrite for HashingWriter<W>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        match Pin::new(&mut this.inner).poll_write(cx, buf) {
+            Poll::Ready(Ok(n)) => {
+                this.hasher.update(&buf[..n]);
+
+                Poll::Ready(Ok(n))
+            }
+            res => res,
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+impl<W> futures_io::AsyncWrite for HashingWriter<W>
+where
+    W: futures_io::AsyncWrite + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        match Pin::new(&mut this.inner).poll_write(cx, buf) {
+            Poll::Ready(Ok(n)) => {
+                this.hasher.update(&buf[..n]);
+
+                Poll::Ready(Ok(n))
+            }
+            res => res,
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
+
+/// Returns the 128-bit `t1ha2` hash value of all the chunks of the stream.
+///
+/// The result is the same as `T1ha2Hasher::finish128()` of a default hasher updated with the chunks.
+///
+/// # Example
+///
+/// ```
+/// use futures::{executor::block_on, stream};
+/// use t1ha::{t1ha2::hash_stream, T1ha2Hasher};
+///
+/// let chunks = stream::iter(vec![&b"hello "[..], &b"world"[..]]);
+///
+/// let mut h = T1ha2Hasher::default();
+/// h.update(b"hello world");
+///
+/// assert_eq!(block_on(hash_stream(chunks)), h.finish128());
+/// ```
+pub async fn hash_stream<S>(stream: S) -> u128
+where
+    S: Stream,
+    S::Item: AsRef<[u8]>,
+{
+    let mut stream = pin!(stream);
+    let mut hasher = T1ha2Hasher::default();
+
+    while let Some(chunk) = poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+        hasher.update(chunk.as_ref());
+    }
+
+    hasher.finish128()
+}
+
+/// Returns the 128-bit `t1ha2` hash value of all the chunks of the fallible stream,
+/// or the first error of the stream.
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+/// use futures::{executor::block_on, stream};
+/// use t1ha::{t1ha2::try_hash_stream, T1ha2Hasher};
+///
+/// let chunks = stream::iter(vec![Ok::<_, io::Error>(&b"hello "[..]), Ok(&b"world"[..])]);
+///
+/// let mut h = T1ha2Hasher::default();
+/// h.update(b"hello world");
+///
+/// assert_eq!(block_on(try_hash_stream(chunks)).unwrap(), h.finish128());
+/// ```
+pub async fn try_hash_stream<S, B, E>(stream: S) -> Result<u128, E>
+where
+    S: Stream<Item = Result<B, E>>,
+    B: AsRef<[u8]>,
+{
+    let mut stream = pin!(stream);
+    let mut hasher = T1ha2Hasher::default();
+
+    while let Some(chunk) = poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+        hasher.update(chunk?.as_ref());
+    }
+
+    Ok(hasher.finish128())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+    use std::vec::Vec;
+
+    use futures::{executor::block_on, stream, AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    fn expected(data: &[u8]) -> u128 {
+        let mut h = T1ha2Hasher::default();
+        h.update(data);
+        h.finish128()
+    }
+
+    #[test]
+    fn test_tokio() {
+        let data = (0..1000).map(|i| i as u8).collect::<Vec<_>>();
+
+        block_on(async {
+            let mut h = T1ha2Hasher::default();
+            tokio::io::copy(&mut &data[..], &mut h).await.unwrap();
+            assert_eq!(h.finish128(), expected(&data));
+
+            let mut reader = HashingReader::new(&data[..]);
+            let mut copy = Vec::new();
+            tokio::io::AsyncReadExt::read_to_end(&mut reader, &mut copy)
+                .await
+                .unwrap();
+            assert_eq!(copy, data);
+            assert_eq!(reader.finish128(), expected(&data));
+
+            let mut writer = HashingWriter::new(Vec::new());
+            tokio::io::AsyncWriteExt::write_all(&mut writer, &data)
+                .await
+                .unwrap();
+            tokio::io::AsyncWriteExt::shutdown(&mut writer)
+                .await
+                .unwrap();
+            assert_eq!(writer.finish128(), expected(&data));
+            assert_eq!(writer.into_inner(), data);
+        })
+    }
+
+    #[test]
+    fn test_futures_io() {
+        let data = (0..1000).map(|i| i as u8).collect::<Vec<_>>();
+
+        block_on(async {
+            let mut h = T1ha2Hasher::default();
+            futures::io::copy(&data[..], &mut h).await.unwrap();
+            assert_eq!(h.finish128(), expected(&data));
+
+            let mut reader = HashingReader::new(&data[..]);
+            let mut copy = Vec::new();
+            AsyncReadExt::read_to_end(&mut reader, &mut copy)
+                .await
+                .unwrap();
+            assert_eq!(copy, data);
+            assert_eq!(reader.finish128(), expected(&data));
+
+            let mut writer = HashingWriter::new(futures::io::Cursor::new(Vec::new()));
+            AsyncWriteExt::write_all(&mut writer, &data).await.unwrap();
+            AsyncWriteExt::close(&mut writer).await.unwrap();
+            assert_eq!(writer.finish128(), expected(&data));
+            assert_eq!(writer.into_inner().into_inner(), data);
+        })
+    }
+
+    #[test]
+    fn test_hash_stream() {
+        let data = (0..1000).map(|i| i as u8).collect::<Vec<_>>();
+
+        assert_eq!(
+            block_on(hash_stream(stream::iter(data.chunks(7)))),
+            expected(&data)
+        );
+        assert_eq!(
+            block_on(try_hash_stream(stream::iter(
+                data.chunks(7).map(Ok::<_, io::Error>)
+            )))
+            .unwrap(),
+            expected(&data)
+        );
+        assert!(block_on(try_hash_stream(stream::iter(vec![
+            Ok(&data[..]),
+            Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+        ])))
+        .is_err());
+    }
+}
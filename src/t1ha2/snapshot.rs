@@ -0,0 +1,306 @@
+//! The serializable state of `T1ha2Hasher`, to checkpoint and resume a stream.
+
+use core::fmt;
+
+use super::{State, T1ha2Hasher, T1ha2Key};
+
+/// The error returned by [`T1ha2Hasher::import_state()`] when the state is malformed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateError {
+    /// The state has a wrong length.
+    InvalidLength(usize),
+    /// The state was exported by an unsupported version of the encoding.
+    UnsupportedVersion(u8),
+    /// The number of the buffered bytes is out of range.
+    InvalidPartial(u8),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateError::InvalidLength(len) => write!(
+                f,
+                "invalid t1ha2 state length {}, expected {}",
+                len,
+                T1ha2Hasher::STATE_SIZE
+            ),
+            StateError::UnsupportedVersion(version) => {
+                write!(f, "unsupported t1ha2 state version {}", version)
+            }
+            StateError::InvalidPartial(partial) => {
+                write!(f, "invalid t1ha2 state with {} buffered bytes", partial)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StateError {}
+
+const VERSION: usize = 0;
+const PARTIAL: usize = 1;
+const TOTAL: usize = 2;
+const KEY: usize = TOTAL + 8;
+const STATE: usize = KEY + 32;
+const BUFFER: usize = STATE + 32;
+
+impl State {
+    fn write_to(&self, buf: &mut [u8]) {
+        for (chunk, v) in buf
+            .chunks_exact_mut(8)
+            .zip([self.a, self.b, self.c, self.d])
+        {
+            chunk.copy_from_slice(&v.to_le_bytes());
+        }
+    }
+
+    fn read_from(buf: &[u8]) -> Self {
+        let mut v = [0; 4];
+
+        for (v, chunk) in v.iter_mut().zip(buf.chunks_exact(8)) {
+            let mut b = [0; 8];
+            b.copy_from_slice(chunk);
+            *v = u64::from_le_bytes(b);
+        }
+
+        State {
+            a: v[0],
+            b: v[1],
+            c: v[2],
+            d: v[3],
+        }
+    }
+}
+
+impl T1ha2Hasher {
+    /// The version of the state encoding produced by [`export_state()`](T1ha2Hasher::export_state).
+    pub const STATE_VERSION: u8 = 1;
+
+    /// The size of the encoded state.
+    pub const STATE_SIZE: usize = BUFFER + 32;
+
+    /// Export the state of the hasher, to resume the stream later with [`import_state()`](T1ha2Hasher::import_state).
+    ///
+    /// The encoding has a fixed layout, all the integers are little-endian:
+    ///
+    /// | Offset | Size | Field                                  |
+    /// | -----: | ---: | :------------------------------------- |
+    /// | 0      | 1    | version, `STATE_VERSION`               |
+    /// | 1      | 1    | the number of the buffered bytes       |
+    /// | 2      | 8    | the total number of the hashed bytes   |
+    /// | 10     | 32   | the state derived from the seeds       |
+    /// | 42     | 32   | the current state                      |
+    /// | 74     | 32   | the buffered bytes, padded with zeros  |
+    ///
+    /// The state contains the seeds, so it should be kept as secret as the seeds.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use t1ha::T1ha2Hasher;
+    ///
+    /// let mut h = T1ha2Hasher::with_seeds(1, 2);
+    /// h.update(b"hello ");
+    ///
+    /// let state = h.export_state();
+    ///
+    /// let mut resumed = T1ha2Hasher::import_state(&state).unwrap();
+    /// resumed.update(b"world");
+    ///
+    /// h.update(b"world");
+    ///
+    /// assert_eq!(resumed.finish128(), h.finish128());
+    /// ```
+    pub fn export_state(&self) -> [u8; T1ha2Hasher::STATE_SIZE] {
+        let mut buf = [0; T1ha2Hasher::STATE_SIZE];
+
+        buf[VERSION] = Self::STATE_VERSION;
+        buf[PARTIAL] = self.partial as u8;
+        buf[TOTAL..KEY].copy_from_slice(&self.total.to_le_bytes());
+        self.key.state.write_to(&mut buf[KEY..STATE]);
+        self.state.write_to(&mut buf[STATE..BUFFER]);
+        buf[BUFFER..BUFFER + self.partial].copy_from_slice(&self.buffer[..self.partial]);
+
+        buf
+    }
+
+    /// Import the state exported by [`export_state()`](T1ha2Hasher::export_state).
+    pub fn import_state(buf: &[u8]) -> Result<Self, StateError> {
+        if buf.len() != Self::STATE_SIZE {
+            return Err(StateError::InvalidLength(buf.len()));
+        }
+        if buf[VERSION] != Self::STATE_VERSION {
+            return Err(StateError::UnsupportedVersion(buf[VERSION]));
+        }
+
+        let partial = buf[PARTIAL] as usize;
+
+        if partial >= 32 {
+            return Err(StateError::InvalidPartial(buf[PARTIAL]));
+        }
+
+        let mut total = [0; 8];
+        total.copy_from_slice(&buf[TOTAL..KEY]);
+
+        let mut buffer = [0; 32];
+        buffer[..partial].copy_from_slice(&buf[BUFFER..BUFFER + partial]);
+
+        Ok(T1ha2Hasher {
+            key: T1ha2Key {
+                state: State::read_from(&buf[KEY..STATE]),
+            },
+            state: State::read_from(&buf[STATE..BUFFER]),
+            buffer,
+            partial,
+            total: u64::from_le_bytes(total),
+        })
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "serde")] {
+        use ::serde::{
+            de::{self, Deserialize, Deserializer, SeqAccess, Visitor},
+            ser::{Serialize, Serializer},
+        };
+
+        /// Serialize the hasher as the bytes of [`T1ha2Hasher::export_state()`].
+        impl Serialize for T1ha2Hasher {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                serializer.serialize_bytes(&self.export_state())
+            }
+        }
+
+        /// Deserialize the hasher from the bytes of [`T1ha2Hasher::export_state()`].
+        impl<'de> Deserialize<'de> for T1ha2Hasher {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                deserializer.deserialize_bytes(StateVisitor)
+            }
+        }
+
+        struct StateVisitor;
+
+        impl<'de> Visitor<'de> for StateVisitor {
+            type Value = T1ha2Hasher;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{} bytes of t1ha2 state", T1ha2Hasher::STATE_SIZE)
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                T1ha2Hasher::import_state(v).map_err(E::custom)
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut buf = [0; T1ha2Hasher::STATE_SIZE];
+                let mut len = 0;
+
+                while let Some(b) = seq.next_element()? {
+                    if len == buf.len() {
+                        return Err(de::Error::invalid_length(len + 1, &self));
+                    }
+
+                    buf[len] = b;
+                    len += 1;
+                }
+
+                self.visit_bytes(&buf[..len])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_import_state() {
+        let data = (0..1000).map(|i| i as u8).collect::<std::vec::Vec<_>>();
+
+        for split in [0, 1, 31, 32, 33, 100, 999, 1000] {
+            let mut h = T1ha2Hasher::with_seeds(1, 2);
+            h.update(&data[..split]);
+
+            let state = h.export_state();
+            assert_eq!(state[VERSION], T1ha2Hasher::STATE_VERSION);
+            assert_eq!(state[PARTIAL] as usize, split % 32);
+
+            let mut resumed = T1ha2Hasher::import_state(&state).unwrap();
+            assert_eq!(resumed.export_state()[..], state[..]);
+
+            resumed.update(&data[split..]);
+            h.update(&data[split..]);
+            assert_eq!(resumed.finish(), h.finish());
+            assert_eq!(resumed.finish128(), h.finish128());
+
+            resumed.reset();
+            resumed.update(&data);
+            assert_eq!(resumed.finish128(), h.finish128());
+        }
+    }
+
+    #[test]
+    fn test_import_invalid_state() {
+        let state = T1ha2Hasher::default().export_state();
+
+        assert_eq!(
+            T1ha2Hasher::import_state(&state[1..]).unwrap_err(),
+            StateError::InvalidLength(T1ha2Hasher::STATE_SIZE - 1)
+        );
+
+        let mut bad = state;
+        bad[VERSION] = 0;
+        assert_eq!(
+            T1ha2Hasher::import_state(&bad).unwrap_err(),
+            StateError::UnsupportedVersion(0)
+        );
+
+        let mut bad = state;
+        bad[PARTIAL] = 32;
+        assert_eq!(
+            T1ha2Hasher::import_state(&bad).unwrap_err(),
+            StateError::InvalidPartial(32)
+        );
+    }
+
+    #[test]
+    fn test_total_u64() {
+        let mut h = T1ha2Hasher {
+            total: 5 << 30,
+            ..Default::default()
+        };
+        h.update(b"hello world");
+
+        let h = T1ha2Hasher::import_state(&h.export_state()).unwrap();
+        assert_eq!(h.total, (5 << 30) + 11);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut h = T1ha2Hasher::with_seeds(1, 2);
+        h.update(b"hello ");
+
+        let json = serde_json::to_string(&h).unwrap();
+        let mut resumed: T1ha2Hasher = serde_json::from_str(&json).unwrap();
+
+        resumed.update(b"world");
+        h.update(b"world");
+        assert_eq!(resumed.finish128(), h.finish128());
+
+        assert!(serde_json::from_str::<T1ha2Hasher>("[1, 2, 3]").is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! The tree mode of `t1ha2`, to hash very large buffers on all the cores.
+//!
+//! The data is split into leaves of `chunk_size` bytes, the last leaf may be shorter.
+//!
+//!   - The leaf `i` is hashed with `T1ha2Key::new(seed, i).hash128(leaf)`.
+//!   - The root is the `finish128()` of a `T1ha2Hasher::with_seeds(seed, seed)` updated with
+//!     `TREE_VERSION`, `chunk_size` and the length of the data as 64-bit little-endian integers,
+//!     followed by the 128-bit little-endian digests of all the leaves in order.
+//!
+//! The leaves are independent, so they may be hashed in parallel,
+//! `hash128_sequential()` and `hash128_parallel()` always return the same value.
+//!
+//! The result depends on `chunk_size`, and differs from `t1ha2_atonce128()` of the same data.
+
+use super::{T1ha2Hasher, T1ha2Key};
+
+/// The version of the tree mode, it will be bumped when the output changes.
+pub const TREE_VERSION: u64 = 1;
+
+/// The default size of a leaf, 1 MiB.
+pub const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+/// Returns the 128-bit hash value of the data in the tree mode.
+///
+/// The leaves are hashed in parallel with the `rayon` feature, otherwise sequentially.
+///
+/// # Panics
+///
+/// Panics if `chunk_size` is zero.
+///
+/// # Example
+///
+/// ```
+/// use t1ha::t1ha2::tree;
+///
+/// let data = vec![0u8; 1 << 16];
+///
+/// assert_eq!(
+///     tree::hash128(&data, 42, 4096),
+///     tree::hash128_sequential(&data, 42, 4096)
+/// );
+/// ```
+pub fn hash128(data: &[u8], seed: u64, chunk_size: usize) -> u128 {
+    cfg_if! {
+        if #[cfg(feature = "rayon")] {
+            hash128_parallel(data, seed, chunk_size)
+        } else {
+            hash128_sequential(data, seed, chunk_size)
+        }
+    }
+}
+
+/// Returns the 128-bit hash value of the data in the tree mode, hashing the leaves one by one.
+///
+/// # Panics
+///
+/// Panics if `chunk_size` is zero.
+pub fn hash128_sequential(data: &[u8], seed: u64, chunk_size: usize) -> u128 {
+    let mut root = root(data, seed, chunk_size);
+
+    for (i, leaf) in data.chunks(chunk_size).enumerate() {
+        root.update(&hash_leaf(leaf, seed, i).to_le_bytes());
+    }
+
+    root.finish128()
+}
+
+/// Returns the 128-bit hash value of the data in the tree mode, hashing the leaves in parallel with `rayon`.
+///
+/// # Panics
+///
+/// Panics if `chunk_size` is zero.
+#[cfg(feature = "rayon")]
+pub fn hash128_parallel(data: &[u8], seed: u64, chunk_size: usize) -> u128 {
+    use rayon::prelude::*;
+    use std::vec::Vec;
+
+    let mut root = root(data, seed, chunk_size);
+
+    let leaves = data
+        .par_chunks(chunk_size)
+        .enumerate()
+        .map(|(i, leaf)| hash_leaf(leaf, seed, i))
+        .collect::<Vec<_>>();
+
+    for leaf in leaves {
+        root.update(&leaf.to_le_bytes());
+    }
+
+    root.finish128()
+}
+
+fn root(data: &[u8], seed: u64, chunk_size: usize) -> T1ha2Hasher {
+    assert!(chunk_size > 0, "chunk size must be non-zero");
+
+    let mut root = T1ha2Hasher::with_seeds(seed, seed);
+
+    root.update(&TREE_VERSION.to_le_bytes());
+    root.update(&(chunk_size as u64).to_le_bytes());
+    root.update(&(data.len() as u64).to_le_bytes());
+    root
+}
+
+#[inline]
+fn hash_leaf(leaf: &[u8], seed: u64, index: usize) -> u128 {
+    T1ha2Key::new(seed, index as u64).hash128(leaf)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn test_tree_mode() {
+        let data = (0..10_000).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+
+        assert_eq!(
+            hash128(&[], 0, DEFAULT_CHUNK_SIZE),
+            0x6425_7856_9292_BAE6_90A7_D58C_5974_622A
+        );
+        assert_eq!(
+            hash128(&data, 42, 1024),
+            0xA332_97D5_8116_E3AF_2644_8E15_E936_206A
+        );
+
+        for &len in &[0, 1, 31, 32, 33, 1000, 1024, 1025, 10_000] {
+            for &chunk_size in &[1, 32, 100, 1024, DEFAULT_CHUNK_SIZE] {
+                let data = &data[..len];
+
+                assert_eq!(
+                    hash128(data, 42, chunk_size),
+                    hash128_sequential(data, 42, chunk_size)
+                );
+            }
+        }
+
+        assert_ne!(hash128(&data, 42, 1024), hash128(&data, 42, 2048));
+        assert_ne!(hash128(&data, 42, 1024), hash128(&data, 43, 1024));
+        assert_ne!(hash128(&data, 42, 1024), hash128(&data[1..], 42, 1024));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel() {
+        let data = (0..100_000).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+
+        for &chunk_size in &[1, 32, 1000, 4096, DEFAULT_CHUNK_SIZE] {
+            assert_eq!(
+                hash128_parallel(&data, 42, chunk_size),
+                hash128_sequential(&data, 42, chunk_size)
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "chunk size must be non-zero")]
+    fn test_zero_chunk_size() {
+        hash128(b"hello world", 0, 0);
+    }
+}
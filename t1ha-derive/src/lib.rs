@@ -0,0 +1,226 @@
+//! The procedural macros of the [`t1ha`](https://docs.rs/t1ha) crate.
+//!
+//! Use them through `t1ha`, which re-exports them as `t1ha::t1ha2!`, and `t1ha::StableHash` with the `derive` feature.
+//!
+//! The fields are hashed in the order of declaration, and an enum hashes the index of the variant
+//! as `u32` before its fields. A field marked with `#[stable_hash(skip)]` is not hashed.
+extern crate proc_macro;
+
+mod t1ha2;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Literal, Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input, parse_quote, Data, DeriveInput, Expr, ExprLit, Fields, Ident, Lit, Token,
+};
+
+/// Derive the `StableHash` trait of the `t1ha` crate.
+#[proc_macro_derive(StableHash, attributes(stable_hash))]
+pub fn derive_stable_hash(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(mut input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::t1ha::StableHash));
+    }
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (pat, stmts) = destructure(&data.fields)?;
+
+            quote! {
+                let #name #pat = *self;
+                #(#stmts)*
+            }
+        }
+        Data::Enum(data) => {
+            let arms = data
+                .variants
+                .iter()
+                .enumerate()
+                .map(|(index, variant)| {
+                    let ident = &variant.ident;
+                    let index = index as u32;
+                    let (pat, stmts) = destructure(&variant.fields)?;
+
+                    Ok(quote! {
+                        #name::#ident #pat => {
+                            hasher.write_u32(#index);
+                            #(#stmts)*
+                        }
+                    })
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+
+            quote! {
+                match *self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "`StableHash` can't be derived for unions",
+            ))
+        }
+    };
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics ::t1ha::StableHash for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn stable_hash(&self, hasher: &mut ::t1ha::StableHasher) {
+                #body
+            }
+        }
+    })
+}
+
+/// Returns the pattern which binds the fields by reference, and the statements which hash them.
+fn destructure(fields: &Fields) -> syn::Result<(TokenStream2, Vec<TokenStream2>)> {
+    let mut bindings = Vec::new();
+    let mut stmts = Vec::new();
+
+    for (index, field) in fields.iter().enumerate() {
+        if is_skipped(&field.attrs)? {
+            bindings.push(quote!(_));
+        } else {
+            let binding = Ident::new(&format!("__field{}", index), Span::call_site());
+
+            stmts.push(quote! {
+                ::t1ha::StableHash::stable_hash(#binding, hasher);
+            });
+            bindings.push(quote!(ref #binding));
+        }
+    }
+
+    let pat = match fields {
+        Fields::Named(named) => {
+            let names = named.named.iter().map(|field| &field.ident);
+
+            quote!({ #(#names: #bindings),* })
+        }
+        Fields::Unnamed(_) => quote!(( #(#bindings),* )),
+        Fields::Unit => quote!(),
+    };
+
+    Ok((pat, stmts))
+}
+
+/// The arguments of `t1ha2!`, a string or byte string literal and an optional seed.
+struct T1ha2Args {
+    data: Vec<u8>,
+    seed: Option<Expr>,
+}
+
+impl Parse for T1ha2Args {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let data = match input.parse()? {
+            Lit::Str(lit) => lit.value().into_bytes(),
+            Lit::ByteStr(lit) => lit.value(),
+            lit => {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    "expected a string or byte string literal",
+                ))
+            }
+        };
+
+        let seed = if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let seed = input.parse()?;
+            input.parse::<Option<Token![,]>>()?;
+            Some(seed)
+        } else {
+            None
+        };
+
+        Ok(T1ha2Args { data, seed })
+    }
+}
+
+/// Hash a string or byte string literal with `t1ha2_atonce()` at compile time.
+///
+/// The optional second argument is the seed, zero by default.
+///
+/// It expands to an `u64` literal when the seed is an integer literal or omitted,
+/// so it could be used as a `match` pattern, otherwise to a constant block.
+///
+/// # Example
+///
+/// ```
+/// use t1ha::{t1ha2, t1ha2_atonce};
+///
+/// const SEED: u64 = 42;
+/// const POST: u64 = t1ha2!(b"POST", SEED);
+///
+/// fn method(name: &str) -> Option<&'static str> {
+///     match t1ha2_atonce(name.as_bytes(), 0) {
+///         t1ha2!("GET") => Some("get"),
+///         t1ha2!("PUT") => Some("put"),
+///         _ => None,
+///     }
+/// }
+///
+/// assert_eq!(method("GET"), Some("get"));
+/// assert_eq!(method("PUT"), Some("put"));
+/// assert_eq!(method("DELETE"), None);
+/// assert_eq!(POST, t1ha2_atonce(b"POST", 42));
+/// ```
+#[proc_macro]
+pub fn t1ha2(input: TokenStream) -> TokenStream {
+    let T1ha2Args { data, seed } = parse_macro_input!(input as T1ha2Args);
+
+    let seed = match seed {
+        None => 0,
+        Some(Expr::Lit(ExprLit {
+            lit: Lit::Int(lit), ..
+        })) => match lit.base10_parse() {
+            Ok(seed) => seed,
+            Err(err) => return err.into_compile_error().into(),
+        },
+        Some(seed) => {
+            let data = Literal::byte_string(&data);
+
+            return quote!({
+                const HASH: u64 = ::t1ha::t1ha2_atonce_const(#data, #seed);
+                HASH
+            })
+            .into();
+        }
+    };
+
+    let hash = Literal::u64_suffixed(t1ha2::t1ha2_atonce(&data, seed));
+
+    quote!(#hash).into()
+}
+
+fn is_skipped(attrs: &[syn::Attribute]) -> syn::Result<bool> {
+    let mut skip = false;
+
+    for attr in attrs {
+        if attr.path().is_ident("stable_hash") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported `stable_hash` attribute, expected `skip`"))
+                }
+            })?;
+        }
+    }
+
+    Ok(skip)
+}
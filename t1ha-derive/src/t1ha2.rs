@@ -0,0 +1,131 @@
+//! A copy of `t1ha2_atonce()`, to hash the literals while expanding the `t1ha2!` macro.
+//!
+//! The proc-macro crate can't depend on `t1ha`, so the tests check it against the reference values of `t1ha`.
+
+const PRIME_0: u64 = 0xEC99_BF0D_8372_CAAB;
+const PRIME_1: u64 = 0x8243_4FE9_0EDC_EF39;
+const PRIME_2: u64 = 0xD4F0_6DB9_9D67_BE4B;
+const PRIME_3: u64 = 0xBD9C_ACC2_2C6E_9571;
+const PRIME_4: u64 = 0x9C06_FAF4_D023_E3AB;
+const PRIME_5: u64 = 0xC060_724A_8424_F345;
+const PRIME_6: u64 = 0xCB5A_F53A_E3AA_AC31;
+
+fn rot64(v: u64, n: u32) -> u64 {
+    v.rotate_right(n)
+}
+
+fn mux64(v: u64, prime: u64) -> u64 {
+    let r = u128::from(v).wrapping_mul(u128::from(prime));
+    (r as u64) ^ ((r >> 64) as u64)
+}
+
+fn final64(a: u64, b: u64) -> u64 {
+    let x = a.wrapping_add(rot64(b, 41)).wrapping_mul(PRIME_0);
+    let y = rot64(a, 23).wrapping_add(b).wrapping_mul(PRIME_6);
+    mux64(x ^ y, PRIME_5)
+}
+
+fn mixup64(a: &mut u64, b: &mut u64, v: u64, prime: u64) {
+    let r = u128::from(b.wrapping_add(v)).wrapping_mul(u128::from(prime));
+    *a ^= r as u64;
+    *b = b.wrapping_add((r >> 64) as u64);
+}
+
+/// Reads the first 1 to 8 bytes as a little-endian word, padded with zeros.
+fn fetch64(data: &[u8]) -> u64 {
+    let mut buf = [0; 8];
+    let n = data.len().min(8);
+    buf[..n].copy_from_slice(&data[..n]);
+    u64::from_le_bytes(buf)
+}
+
+pub fn t1ha2_atonce(data: &[u8], seed: u64) -> u64 {
+    let len = data.len() as u64;
+    let mut a = seed;
+    let mut b = len;
+    let mut rest = data;
+
+    if data.len() > 32 {
+        let mut c = rot64(len, 23).wrapping_add(!seed);
+        let mut d = (!len).wrapping_add(rot64(seed, 19));
+
+        while rest.len() >= 32 {
+            let w0 = fetch64(&rest[0..]);
+            let w1 = fetch64(&rest[8..]);
+            let w2 = fetch64(&rest[16..]);
+            let w3 = fetch64(&rest[24..]);
+            rest = &rest[32..];
+
+            let d02 = w0.wrapping_add(rot64(w2.wrapping_add(d), 56));
+            let c13 = w1.wrapping_add(rot64(w3.wrapping_add(c), 19));
+            let (a0, b0) = (a, b);
+
+            a ^= PRIME_5.wrapping_mul(d02.wrapping_add(w3));
+            b ^= PRIME_6.wrapping_mul(c13.wrapping_add(w2));
+            c ^= a0.wrapping_add(rot64(w0, 57));
+            d ^= b0.wrapping_add(rot64(w1, 38));
+        }
+
+        a ^= PRIME_6.wrapping_mul(c.wrapping_add(rot64(d, 23)));
+        b ^= PRIME_5.wrapping_mul(rot64(c, 19).wrapping_add(d));
+    }
+
+    match rest.len() {
+        25..=32 => {
+            mixup64(&mut a, &mut b, fetch64(rest), PRIME_4);
+            mixup64(&mut b, &mut a, fetch64(&rest[8..]), PRIME_3);
+            mixup64(&mut a, &mut b, fetch64(&rest[16..]), PRIME_2);
+            mixup64(&mut b, &mut a, fetch64(&rest[24..]), PRIME_1);
+        }
+        17..=24 => {
+            mixup64(&mut b, &mut a, fetch64(rest), PRIME_3);
+            mixup64(&mut a, &mut b, fetch64(&rest[8..]), PRIME_2);
+            mixup64(&mut b, &mut a, fetch64(&rest[16..]), PRIME_1);
+        }
+        9..=16 => {
+            mixup64(&mut a, &mut b, fetch64(rest), PRIME_2);
+            mixup64(&mut b, &mut a, fetch64(&rest[8..]), PRIME_1);
+        }
+        1..=8 => {
+            mixup64(&mut b, &mut a, fetch64(rest), PRIME_1);
+        }
+        _ => {}
+    }
+
+    final64(a, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_values() {
+        t1ha::selfcheck::check(
+            "t1ha2_atonce",
+            t1ha2_atonce,
+            &t1ha::selfcheck::T1HA_REFVAL_2ATONCE,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_lengths() {
+        let data = (0..256).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+
+        // the whole 32-byte blocks, with and without the tails of every length.
+        for len in (0..=100).chain([128, 255]) {
+            let data = &data[..len];
+
+            for &seed in &[0, 42, u64::MAX] {
+                assert_eq!(
+                    t1ha2_atonce(data, seed),
+                    t1ha::t1ha2_atonce(data, seed),
+                    "len = {}, seed = {}",
+                    len,
+                    seed
+                );
+            }
+        }
+    }
+}